@@ -0,0 +1,25 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tiny_fail::Fail;
+
+fn deep_fail() -> Fail {
+    let mut fail = Fail::new("no such file");
+    for i in 0..20 {
+        fail = fail.context(format!("failed step {}", i));
+    }
+    fail
+}
+
+fn repeated_display(c: &mut Criterion) {
+    let live = deep_fail();
+    let frozen = deep_fail().freeze();
+
+    let mut group = c.benchmark_group("repeated_display");
+    group.bench_function("live", |b| b.iter(|| black_box(&live).to_string()));
+    group.bench_function("frozen", |b| b.iter(|| black_box(&frozen).to_string()));
+    group.finish();
+}
+
+criterion_group!(benches, repeated_display);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tiny_fail::{Fail, FailExt};
+
+fn parse_plain(inputs: &[&str]) -> Result<u64, std::num::ParseIntError> {
+    let mut sum = 0;
+    for input in inputs {
+        sum += input.parse::<u64>()?;
+    }
+    Ok(sum)
+}
+
+fn parse_with_context(inputs: &[&str]) -> Result<u64, Fail> {
+    let mut sum = 0;
+    for input in inputs {
+        sum += input.parse::<u64>().context("failed parse")?;
+    }
+    Ok(sum)
+}
+
+fn parse_with_from(inputs: &[&str]) -> Result<u64, Fail> {
+    let mut sum = 0;
+    for input in inputs {
+        sum += input.parse::<u64>()?;
+    }
+    Ok(sum)
+}
+
+fn happy_path(c: &mut Criterion) {
+    let inputs: Vec<&str> = (0..1000).map(|_| "12345").collect();
+
+    let mut group = c.benchmark_group("happy_path");
+    group.bench_function("plain", |b| b.iter(|| parse_plain(black_box(&inputs))));
+    group.bench_function("context", |b| {
+        b.iter(|| parse_with_context(black_box(&inputs)))
+    });
+    group.bench_function("from", |b| b.iter(|| parse_with_from(black_box(&inputs))));
+    group.finish();
+}
+
+criterion_group!(benches, happy_path);
+criterion_main!(benches);
@@ -0,0 +1,108 @@
+use std::error;
+use std::fmt;
+
+use crate::{Error, Fail};
+
+impl Fail {
+    /// Converts `eyre::Report` into `Fail`, keeping each message of its chain as a layer.
+    ///
+    /// The report is kept as the deepest cause, so its root cause can be found by `downcast_ref`.
+    ///
+    /// `From<eyre::Report>` can't be implemented because it would overlap with the conversion from `std::error::Error` types.
+    #[track_caller]
+    pub fn from_eyre(report: eyre::Report) -> Fail {
+        let mut msgs: Vec<String> = report.chain().map(|e| e.to_string()).collect();
+        msgs.pop();
+
+        let mut fail = Fail::from_boxed(Box::new(RootCause(report)));
+        for msg in msgs.into_iter().rev() {
+            fail = Fail::from_parts(Some(msg), Some(fail));
+        }
+        fail
+    }
+}
+
+/// Shows the root cause of the report, whose outer messages are already layers.
+#[derive(Debug)]
+struct RootCause(eyre::Report);
+
+impl fmt::Display for RootCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0.root_cause(), f)
+    }
+}
+
+impl error::Error for RootCause {
+    fn source(&self) -> Option<&(dyn 'static + error::Error)> {
+        Some(self.0.root_cause())
+    }
+}
+
+impl From<Fail> for eyre::Report {
+    fn from(fail: Fail) -> eyre::Report {
+        eyre::Report::new(Error(fail))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use eyre::WrapErr;
+
+    use crate::{Fail, FailExt};
+
+    #[test]
+    fn from_eyre_keeps_chain() {
+        let report = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            .wrap_err("failed open")
+            .wrap_err("failed load")
+            .unwrap_err();
+
+        let fail = Fail::from_eyre(report);
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+
+        let report = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            .wrap_err("failed open")
+            .unwrap_err();
+        let fail = Fail::from_eyre(report).context("failed load");
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+
+        let fail = Fail::from_eyre(eyre::eyre!("plain"));
+        assert_eq!(fail.to_string(), "plain");
+    }
+
+    #[test]
+    fn from_eyre_keeps_leaf_error() {
+        let report = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            .wrap_err("failed open")
+            .unwrap_err();
+
+        let fail = Fail::from_eyre(report).context("failed load");
+        let err = fail.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+
+        let fail = Err::<(), _>(fail).context_flatten("top").unwrap_err();
+        assert_eq!(
+            fail.to_string(),
+            "top: failed load: failed open: no such file"
+        );
+    }
+
+    #[test]
+    fn into_eyre_keeps_chain() {
+        let fail = Fail::new("no such file")
+            .context("failed open")
+            .context("failed load");
+
+        let report = eyre::Report::from(fail);
+        assert_eq!(report.to_string(), "failed load");
+
+        let chain: Vec<String> = report.chain().map(|e| e.to_string()).collect();
+        assert_eq!(chain, vec!["failed load", "failed open", "no such file"]);
+
+        let fail = Fail::from_eyre(report);
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+    }
+}
@@ -5,13 +5,53 @@
 //! * [`Error`](struct.Error.html) is a wrapper for `Fail` that implements `std::error::Error`.
 //!
 //! [`FailExt`](trait.FailExt.html) is supprting trait. It helps to handling `Result` and `Option`.
+//!
+//! # Features
+//!
+//! * `eyre`: Converts between [`Fail`](struct.Fail.html) and `eyre::Report`. See [`Fail::from_eyre`](struct.Fail.html#method.from_eyre).
+//! * `log`: Adds [`Fail::log`](struct.Fail.html#method.log) that emits the failure by the `log` crate.
+//! * `miette`: Implements `miette::Diagnostic` for [`Error`](struct.Error.html).
+//! * `serde`: Implements `serde::Serialize` and `serde::Deserialize` for [`Fail`](struct.Fail.html).
+//! * `spantrace`: Captures `tracing_error::SpanTrace` where a `Fail` occurs. See [`Fail::span_trace`](struct.Fail.html#method.span_trace).
+//! * `timestamp` (default): Records the time when each `Fail` layer is created. See [`Fail::timestamp`](struct.Fail.html#method.timestamp).
+//! * `tracing`: Adds [`Fail::emit`](struct.Fail.html#method.emit) that records the failure as a `tracing` event.
 
+use std::borrow::Cow;
+use std::env;
 use std::error;
+use std::ffi::OsStr;
 use std::fmt;
+use std::future::Future;
+use std::io;
+use std::iter::FromIterator;
+use std::panic;
+use std::path::Path;
+use std::pin::Pin;
 use std::string::ToString;
+use std::sync::{OnceLock, PoisonError};
+use std::task::{self, Poll};
+use std::time::{Instant, SystemTime};
+
+#[cfg(feature = "eyre")]
+mod eyre_impl;
+#[cfg(feature = "log")]
+mod log_impl;
+#[cfg(feature = "miette")]
+mod miette_impl;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "spantrace")]
+mod spantrace_impl;
+#[cfg(feature = "tracing")]
+mod tracing_impl;
 
 /// The failure type.
 ///
+/// `Fail` is displayed as a single line like `high level message: low level message`.
+/// The alternate form (`{:#}`) shows one message per line, prefixed with `caused by: `.
+/// The alternate form is also used by default when the environment variable `TINY_FAIL_VERBOSE` is `1`.
+/// With the `spantrace` feature, the alternate form also shows the captured span trace after the chain.
+///
 /// # Example
 ///
 /// ```
@@ -35,198 +75,4832 @@ use std::string::ToString;
 ///     Ok(())
 /// }
 /// ```
-#[derive(Debug)]
 pub struct Fail {
-    msg: Option<String>,
+    inner: Box<FailInner>,
+}
+
+/// Contents of `Fail`, boxed to make `Fail` one pointer wide.
+struct FailInner {
+    msg: Option<Cow<'static, str>>,
     cause: Option<FailCause>,
+    meta: Option<Box<Meta>>,
+    location: Option<Location>,
+    #[cfg(feature = "spantrace")]
+    span_trace: Option<tracing_error::SpanTrace>,
+    #[cfg(feature = "timestamp")]
+    timestamp: SystemTime,
+}
+
+// `Fail` and `Error` must stay `'static + Send + Sync` whatever fields are added.
+const _: fn() = || {
+    fn assert<T: 'static + Send + Sync>() {}
+    assert::<Fail>();
+    assert::<Error>();
+};
+
+/// A source location where a [`Fail`](struct.Fail.html) layer was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl Location {
+    /// Returns the file name.
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// Returns the line number.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the column number.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl From<&panic::Location<'static>> for Location {
+    fn from(loc: &panic::Location<'static>) -> Location {
+        Location {
+            file: loc.file(),
+            line: loc.line(),
+            column: loc.column(),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Rarely used properties of `Fail`, allocated only when one is set.
+#[derive(Debug, Default)]
+struct Meta {
+    fields: Vec<(String, String)>,
+    notes: Vec<String>,
+    suggestion: Option<String>,
+    separator: Option<&'static str>,
+    str_code: Option<Cow<'static, str>>,
+    exit_code: Option<i32>,
+    transient: Option<bool>,
+    category: Option<Category>,
+    io_kind: Option<io::ErrorKind>,
 }
 
 #[derive(Debug)]
 enum FailCause {
     Error(Box<dyn 'static + Send + Sync + error::Error>),
-    Fail(Box<Fail>),
+    Fail(Fail),
+    Multiple(Vec<Fail>),
 }
 
-impl Fail {
-    /// Create new `Fail` from message.
-    pub fn new<S: ToString>(msg: S) -> Fail {
-        Fail {
-            msg: Some(msg.to_string()),
-            cause: None,
+/// The field key used by [`FailExt::context_structured`](trait.FailExt.html#tymethod.context_structured) to record the original error.
+pub const SOURCE_FIELD: &str = "source";
+
+/// A coarse category of a [`Fail`](struct.Fail.html), returned by [`Fail::category`](struct.Fail.html#method.category).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Category {
+    /// Something is not found.
+    NotFound,
+    /// The operation lacks permissions.
+    PermissionDenied,
+    /// Something already exists.
+    AlreadyExists,
+    /// The input is invalid.
+    InvalidInput,
+    /// The operation timed out.
+    Timeout,
+    /// Any other failures.
+    Other,
+}
+
+impl Category {
+    fn from_io_kind(kind: io::ErrorKind) -> Category {
+        match kind {
+            io::ErrorKind::NotFound => Category::NotFound,
+            io::ErrorKind::PermissionDenied => Category::PermissionDenied,
+            io::ErrorKind::AlreadyExists => Category::AlreadyExists,
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => Category::InvalidInput,
+            io::ErrorKind::TimedOut => Category::Timeout,
+            _ => Category::Other,
         }
     }
+}
 
-    fn add_msg<S: ToString>(self, msg: S) -> Fail {
-        match self {
-            Fail { msg: None, cause } => Fail {
-                msg: Some(msg.to_string()),
-                cause,
-            },
-            fail => Fail {
-                msg: Some(msg.to_string()),
-                cause: Some(FailCause::Fail(Box::new(fail))),
-            },
+/// A reference to the cause of a [`Fail`](struct.Fail.html), returned by [`Fail::cause`](struct.Fail.html#method.cause).
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum CauseRef<'a> {
+    /// The `Fail` has no cause.
+    None,
+    /// The cause is a foreign error.
+    Error(&'a (dyn 'static + error::Error)),
+    /// The cause is another `Fail`.
+    Fail(&'a Fail),
+    /// The cause is aggregated multiple `Fail`s.
+    Multiple(&'a [Fail]),
+}
+
+impl FailCause {
+    fn from_error<E: 'static + Send + Sync + error::Error>(err: E) -> FailCause {
+        FailCause::from_boxed(Box::new(err))
+    }
+
+    fn from_boxed(err: Box<dyn 'static + Send + Sync + error::Error>) -> FailCause {
+        // `Error` is unwrapped instead of being boxed as a foreign error.
+        match err.downcast::<Error>() {
+            Ok(wrapper) => FailCause::Fail(wrapper.0),
+            Err(err) => FailCause::Error(err),
         }
     }
 }
 
-impl fmt::Display for Fail {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let has_msg = if let Some(msg) = &self.msg {
-            write!(f, "{}", msg)?;
-            true
-        } else {
-            false
+impl Fail {
+    /// Create new `Fail` from message.
+    #[cold]
+    #[track_caller]
+    pub fn new<S: ToString>(msg: S) -> Fail {
+        Fail::from_raw(Some(msg.to_string().into()), None).at_caller()
+    }
+
+    /// Create new `Fail` from a value that describes the failure, but doesn't implement `Error`.
+    ///
+    /// This is the same as `new`, but shows that the value itself is the failure.
+    #[cold]
+    #[track_caller]
+    pub fn from_display<D: fmt::Display>(value: D) -> Fail {
+        Fail::from_raw(Some(value.to_string().into()), None).at_caller()
+    }
+
+    /// Create new `Fail` from message, returning `fmt::Error` if formatting `msg` fails.
+    ///
+    /// `new` panics if the `Display` impl of `msg` returns an error. This doesn't.
+    #[cold]
+    #[track_caller]
+    pub fn try_new<S: fmt::Display>(msg: S) -> Result<Fail, fmt::Error> {
+        let mut buf = String::new();
+        fmt::Write::write_fmt(&mut buf, format_args!("{}", msg))?;
+        Ok(Fail::from_raw(Some(buf.into()), None).at_caller())
+    }
+
+    /// Create new `Fail` from static message.
+    ///
+    /// Unlike `new`, this doesn't allocate for the message.
+    #[track_caller]
+    pub fn new_static(msg: &'static str) -> Fail {
+        Fail::from_raw(Some(Cow::Borrowed(msg)), None).at_caller()
+    }
+
+    /// Create new `Fail` from boxed error, like `Box<dyn Error + Send + Sync>` returned by other libraries.
+    ///
+    /// `From` can't be implemented for the box, since it would overlap with the conversion from `std::error::Error` types.
+    #[cold]
+    #[track_caller]
+    pub fn from_boxed(err: Box<dyn 'static + Send + Sync + error::Error>) -> Fail {
+        let cause = match FailCause::from_boxed(err) {
+            FailCause::Fail(fail) => return fail,
+            cause => cause,
         };
+        let mut fail = Fail::from_raw(None, None).at_caller();
+        fail.set_cause(cause);
+        fail
+    }
 
-        if let Some(cause) = &self.cause {
-            if has_msg {
-                write!(f, ": ")?;
-            }
+    /// Create new `Fail` from `PoisonError`, dropping its guard.
+    ///
+    /// The returned `Fail` has only the message `lock poisoned`.
+    #[track_caller]
+    pub fn from_poison<G>(_err: PoisonError<G>) -> Fail {
+        Fail::new_static(POISONED_MSG)
+    }
 
-            match cause {
-                FailCause::Error(e) => e.fmt(f)?,
-                FailCause::Fail(fail) => fail.fmt(f)?,
-            }
+    /// Create new `Fail` from message and its cause.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::Fail;
+    ///
+    /// let err = io::Error::new(io::ErrorKind::NotFound, "no such file");
+    /// let fail = Fail::with_cause("failed open", err);
+    /// assert_eq!(fail.to_string(), "failed open: no such file");
+    /// ```
+    #[cold]
+    #[track_caller]
+    pub fn with_cause<S: ToString, E: 'static + Send + Sync + error::Error>(
+        msg: S,
+        cause: E,
+    ) -> Fail {
+        let mut fail = Fail::from_raw(Some(msg.to_string().into()), None).at_caller();
+        fail.set_cause(FailCause::from_error(cause));
+        fail
+    }
+
+    /// Attaches `err` as the cause of this `Fail`.
+    ///
+    /// This is intended for a message-only `Fail` like `Fail::new("x").caused_by(err)`.
+    /// If this `Fail` already has a cause, it is replaced.
+    pub fn caused_by<E: 'static + Send + Sync + error::Error>(mut self, err: E) -> Fail {
+        self.set_cause(FailCause::from_error(err));
+        self
+    }
+
+    /// Replaces the deepest cause of the chain with `new_cause`, keeping all message layers.
+    ///
+    /// A foreign error or aggregated `Fail`s at the end of the chain are dropped.
+    /// If the chain has no cause at the end, `new_cause` is simply attached to the deepest layer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("failed query")
+    ///     .caused_by(io::Error::new(io::ErrorKind::Other, "password=secret rejected"));
+    /// let fail = fail.replace_cause(Fail::new("internal error"));
+    /// assert_eq!(fail.to_string(), "failed query: internal error");
+    /// ```
+    pub fn replace_cause(mut self, new_cause: Fail) -> Fail {
+        self.deepest_mut().set_cause(FailCause::Fail(new_cause));
+        self
+    }
+
+    /// Sets the cause of this layer, recording the `ErrorKind` if it is an `io::Error`.
+    fn set_cause(&mut self, cause: FailCause) {
+        let kind = match &cause {
+            FailCause::Error(e) => e.downcast_ref::<io::Error>().map(io::Error::kind),
+            _ => None,
+        };
+        self.inner.cause = Some(cause);
+        match kind {
+            Some(kind) => self.meta_mut().io_kind = Some(kind),
+            None => self.forget_io_kind(),
         }
+    }
 
-        Ok(())
+    /// Clears the recorded `ErrorKind` after the foreign error of this layer is removed.
+    fn forget_io_kind(&mut self) {
+        if let Some(meta) = &mut self.inner.meta {
+            meta.io_kind = None;
+        }
     }
-}
 
-impl<E: 'static + Send + Sync + error::Error> From<E> for Fail {
-    fn from(err: E) -> Fail {
+    /// Returns the deepest layer, the last one of `layers()`.
+    fn deepest_mut(&mut self) -> &mut Fail {
+        let depth = self.layers().count() - 1;
+        let mut last = self;
+        for _ in 0..depth {
+            last = match &mut last.inner.cause {
+                Some(FailCause::Fail(inner)) => inner,
+                _ => unreachable!("layers() follows only Fail causes"),
+            };
+        }
+        last
+    }
+
+    /// Create new `Fail` from message and another `Fail` as its cause.
+    ///
+    /// This is useful to reconstruct a chain, for example, from a serialized form.
+    pub fn from_parts(msg: Option<String>, cause: Option<Fail>) -> Fail {
+        Fail::from_raw(msg.map(Cow::Owned), cause.map(FailCause::Fail))
+    }
+
+    /// Wraps this `Fail` into `Error`, which implements `std::error::Error`.
+    pub fn into_error(self) -> Error {
+        Error(self)
+    }
+
+    /// Wraps this `Fail` into boxed `Error` trait object.
+    pub fn erase(self) -> Box<dyn 'static + Send + Sync + error::Error> {
+        Box::new(self.into_error())
+    }
+
+    /// Converts this `Fail` into `io::Error`.
+    ///
+    /// The `ErrorKind` of the root `io::Error` is reused if it exists, otherwise `ErrorKind::Other` is used.
+    pub fn into_io_error(self) -> io::Error {
+        let kind = self
+            .leaf_error()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+            .map_or(io::ErrorKind::Other, io::Error::kind);
+        io::Error::new(kind, self.into_error())
+    }
+
+    /// Decomposes this layer into its message and the rest of the chain.
+    ///
+    /// This is the inverse of `from_parts`.
+    /// A foreign error is returned wrapped as `Fail`, like `from_boxed`.
+    pub fn take_cause(mut self) -> (Option<String>, Option<Fail>) {
+        let msg = self.take_message();
+        let cause = match self.inner.cause.take() {
+            Some(FailCause::Fail(fail)) => Some(fail),
+            Some(cause @ FailCause::Multiple(_)) => Some(Fail::from_raw(None, Some(cause))),
+            Some(FailCause::Error(e)) => Some(Fail::from_boxed(e)),
+            None => None,
+        };
+        (msg, cause)
+    }
+
+    /// Removes the message of this layer, keeping the cause.
+    pub fn take_message(&mut self) -> Option<String> {
+        self.inner.msg.take().map(Cow::into_owned)
+    }
+
+    #[track_caller]
+    fn at_caller(mut self) -> Fail {
+        self.inner.location = Some(panic::Location::caller().into());
+        self
+    }
+
+    #[inline(never)]
+    fn from_raw(msg: Option<Cow<'static, str>>, cause: Option<FailCause>) -> Fail {
+        // Span trace is captured only where the failure occurs, not on added contexts.
+        #[cfg(feature = "spantrace")]
+        let span_trace = match &cause {
+            Some(FailCause::Fail(_)) => None,
+            _ => spantrace_impl::capture(),
+        };
+
         Fail {
-            msg: None,
-            cause: Some(FailCause::Error(Box::new(err))),
+            inner: Box::new(FailInner {
+                msg,
+                cause,
+                meta: None,
+                location: None,
+                #[cfg(feature = "spantrace")]
+                span_trace,
+                #[cfg(feature = "timestamp")]
+                timestamp: SystemTime::now(),
+            }),
         }
     }
-}
 
-/// A wrapper of `Fail` implements `std::error::Error`.
-#[derive(Debug)]
-pub struct Error(Fail);
+    /// Returns the time when the outermost layer was created.
+    ///
+    /// This is always `None` without the `timestamp` feature.
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        #[cfg(feature = "timestamp")]
+        {
+            Some(self.inner.timestamp)
+        }
+        #[cfg(not(feature = "timestamp"))]
+        {
+            None
+        }
+    }
 
-impl Error {
-    pub fn as_fail(&self) -> &Fail {
-        &self.0
+    /// Returns `true` if this `Fail` has neither messages nor causes.
+    pub fn is_empty(&self) -> bool {
+        self.links().next().is_none()
     }
 
-    pub fn into_fail(self) -> Fail {
-        self.0
+    fn renders_empty(&self) -> bool {
+        self.links().all(|link| match link {
+            Link::Message(msg) => msg.is_empty(),
+            Link::Error(e) => e.to_string().is_empty(),
+            Link::Multiple(_) => false,
+        })
     }
-}
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.fmt(f)
+    /// Returns the cause of this `Fail`.
+    pub fn cause(&self) -> CauseRef<'_> {
+        match &self.inner.cause {
+            None => CauseRef::None,
+            Some(FailCause::Error(e)) => CauseRef::Error(e.as_ref()),
+            Some(FailCause::Fail(fail)) => CauseRef::Fail(fail),
+            Some(FailCause::Multiple(fails)) => CauseRef::Multiple(fails),
+        }
     }
-}
 
-impl error::Error for Error {}
+    /// Returns `true` if any message in the chain contains `needle`.
+    ///
+    /// Messages of foreign errors are not searched. Use `chain_contains` for them.
+    pub fn message_contains(&self, needle: &str) -> bool {
+        self.layers()
+            .filter_map(|fail| fail.inner.msg.as_deref())
+            .any(|msg| msg.contains(needle))
+    }
 
-/// A support trait for implementing rich error message.
-///
-/// This trait implemented for `Option` and `Result`.
-/// You can
-///
-/// # Example
-/// ```
-/// use std::fs::File;
-/// use std::io::{BufRead, BufReader};
-///
-/// use tiny_fail::{Fail, FailExt};
-/// #
-/// # if let Err(e) = foo() {
-/// #     eprintln!("{}", e);
-/// # }
-///
-/// fn foo() -> Result<(), Fail> {
-///     let f = File::open("not_exists.txt").context("failed open not_exists.txt")?;
-///     let r = BufReader::new(f);
-///
-///     for line in r.lines() {
-///         let line = line.context("failed read line")?;
-///         let num = line.parse::<f64>().context(format!("failed parse \"{}\" into floating number", line))?;
-///         println!("{:.2}", num);
-///     }
-///     Ok(())
-/// }
-/// ```
-pub trait FailExt<T> {
-    fn context<S: ToString>(self, msg: S) -> Result<T, Fail>;
-}
+    /// Returns `true` if the rendered chain, including foreign errors, contains `needle`.
+    pub fn chain_contains(&self, needle: &str) -> bool {
+        Joined {
+            fail: self,
+            sep: ": ",
+        }
+        .to_string()
+        .contains(needle)
+    }
 
-impl<T, E: 'static + Send + Sync + error::Error> FailExt<T> for Result<T, E> {
-    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
-        self.map_err(|err| Fail {
-            msg: Some(msg.to_string()),
-            cause: Some(FailCause::Error(Box::new(err))),
+    /// Returns `true` if any single layer, including foreign errors and aggregated `Fail`s, contains `substr`.
+    ///
+    /// Unlike `chain_contains`, a match never spans over separators between layers.
+    pub fn contains_message(&self, substr: &str) -> bool {
+        self.walk().any(|fail| {
+            fail.inner
+                .msg
+                .as_deref()
+                .is_some_and(|msg| msg.contains(substr))
+                || match &fail.inner.cause {
+                    Some(FailCause::Error(e)) => e.to_string().contains(substr),
+                    _ => false,
+                }
         })
     }
-}
 
-impl<T> FailExt<T> for Option<T> {
-    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
-        self.ok_or_else(|| Fail::new(msg))
+    /// Attaches a supplementary note to this `Fail`.
+    ///
+    /// Notes are not shown by `Display`. They are intended for hints to users.
+    pub fn with_note<S: ToString>(mut self, note: S) -> Fail {
+        self.meta_mut().notes.push(note.to_string());
+        self
     }
-}
 
-impl<T> FailExt<T> for Result<T, Fail> {
-    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
-        self.map_err(|fail| fail.add_msg(msg))
+    /// Returns notes attached to the whole chain, from the outermost layer.
+    pub fn notes(&self) -> impl Iterator<Item = &str> {
+        self.layers()
+            .filter_map(|fail| fail.inner.meta.as_deref())
+            .flat_map(|meta| meta.notes.iter().map(String::as_str))
     }
-}
 
-/// Macro to format and return `Err(Fail::new(..))`.
-///
-/// Arguments format is same as [`std::format!()`](https://doc.rust-lang.org/std/macro.format.html).
-///
-/// # Example
-///
-/// ```
-/// use tiny_fail::{raise, Fail};
-/// #
-/// # fn main() {
-/// #     let err = foo().unwrap_err();
-/// #     assert_eq!(&format!("{}", err), "Some error caused by 123");
-/// # }
-///
-/// fn foo() -> Result<(), Fail> {
-///     raise!("Some error caused by {}", 123);
-/// }
-/// ```
-#[macro_export]
-macro_rules! raise {
-    ($($arg:tt)*) => {
-        return std::result::Result::Err($crate::Fail::new(std::format!($($arg)*)))
-    };
-}
+    /// Attaches a "did you mean" suggestion, like a similar name of unknown subcommand.
+    ///
+    /// The alternate `Display` shows it after the chain as `help: did you mean '<s>'?`.
+    pub fn with_suggestion<S: ToString>(mut self, s: S) -> Fail {
+        self.meta_mut().suggestion = Some(s.to_string());
+        self
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Returns the suggestion of the outermost layer that has one.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.layers()
+            .filter_map(|fail| fail.inner.meta.as_deref())
+            .find_map(|meta| meta.suggestion.as_deref())
+    }
 
-    fn assert_send<T: Send>() {}
-    fn assert_sync<T: Sync>() {}
+    /// Sets the separator between messages in the single-line `Display`, instead of `": "`.
+    ///
+    /// The separator of the outermost layer that has one is used. The alternate form is not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low").context("high").with_separator(" -> ");
+    /// assert_eq!(fail.to_string(), "high -> low");
+    /// ```
+    pub fn with_separator(mut self, sep: &'static str) -> Fail {
+        self.meta_mut().separator = Some(sep);
+        self
+    }
 
-    #[test]
-    fn fail_is_send() {
-        assert_send::<Fail>();
+    /// Sets string error code like `"E_NOT_FOUND"` to this `Fail`.
+    ///
+    /// This is independent of messages. It is intended for machine-readable identifiers of failures.
+    pub fn with_str_code<S: Into<String>>(mut self, code: S) -> Fail {
+        self.meta_mut().str_code = Some(Cow::Owned(code.into()));
+        self
     }
 
-    #[test]
-    fn fail_is_sync() {
-        assert_sync::<Fail>();
+    /// Sets static error code like `"E_NOT_FOUND"` to this `Fail`, without allocation.
+    ///
+    /// This shares the storage with `with_str_code`, so the later call wins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("no such user")
+    ///     .with_code("E_NOT_FOUND")
+    ///     .context("failed get user");
+    /// assert_eq!(fail.code(), Some("E_NOT_FOUND"));
+    /// ```
+    pub fn with_code(mut self, code: &'static str) -> Fail {
+        self.meta_mut().str_code = Some(Cow::Borrowed(code));
+        self
     }
 
-    #[test]
-    fn error_is_send() {
-        assert_send::<Error>();
+    /// Returns the error code of the outermost layer that has one. Same as `str_code`.
+    pub fn code(&self) -> Option<&str> {
+        self.str_code()
     }
 
-    #[test]
-    fn error_is_sync() {
-        assert_sync::<Error>();
+    /// Returns the string error code of the outermost layer that has one.
+    pub fn str_code(&self) -> Option<&str> {
+        self.layers()
+            .filter_map(|fail| fail.inner.meta.as_deref())
+            .find_map(|meta| meta.str_code.as_deref())
+    }
+
+    /// Sets process exit code to this `Fail`.
+    pub fn with_exit_code(mut self, code: i32) -> Fail {
+        self.meta_mut().exit_code = Some(code);
+        self
+    }
+
+    /// Returns the exit code of the outermost layer that has one.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.layers()
+            .filter_map(|fail| fail.inner.meta.as_deref())
+            .find_map(|meta| meta.exit_code)
+    }
+
+    /// Marks this `Fail` as transient, that is, worth retrying.
+    pub fn transient(mut self) -> Fail {
+        self.meta_mut().transient = Some(true);
+        self
+    }
+
+    /// Marks this `Fail` as permanent, that is, not worth retrying.
+    pub fn permanent(mut self) -> Fail {
+        self.meta_mut().transient = Some(false);
+        self
+    }
+
+    /// Returns `true` if this `Fail` is worth retrying.
+    ///
+    /// The flag of the outermost layer that has one is used.
+    /// If no layer has the flag and the deepest cause is `io::Error`,
+    /// it is transient if its kind is `WouldBlock`, `TimedOut` or `Interrupted`.
+    /// Otherwise, it is not transient.
+    pub fn is_transient(&self) -> bool {
+        let flag = self
+            .layers()
+            .filter_map(|fail| fail.inner.meta.as_deref())
+            .find_map(|meta| meta.transient);
+        if let Some(flag) = flag {
+            return flag;
+        }
+
+        match self
+            .leaf_error()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+        {
+            Some(e) => matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+            ),
+            None => false,
+        }
+    }
+
+    /// Sets the category to this `Fail`.
+    pub fn with_category(mut self, category: Category) -> Fail {
+        self.meta_mut().category = Some(category);
+        self
+    }
+
+    /// Returns the category of the outermost layer that has one.
+    ///
+    /// If no layer has a category and the deepest cause is `io::Error`, it is inferred from the `ErrorKind`.
+    /// Otherwise, it is `Category::Other`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::{Category, Fail};
+    ///
+    /// let fail = Fail::from(io::Error::from(io::ErrorKind::NotFound)).context("failed open");
+    /// assert_eq!(fail.category(), Category::NotFound);
+    /// ```
+    pub fn category(&self) -> Category {
+        let category = self
+            .layers()
+            .filter_map(|fail| fail.inner.meta.as_deref())
+            .find_map(|meta| meta.category);
+        if let Some(category) = category {
+            return category;
+        }
+
+        match self
+            .leaf_error()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+        {
+            Some(e) => Category::from_io_kind(e.kind()),
+            None => Category::Other,
+        }
+    }
+
+    /// Attaches key-value field to this layer, for structured logging.
+    ///
+    /// Fields are not shown by `Display`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("failed request").with_field("status", 503);
+    /// assert_eq!(fail.fields(), &[("status".to_string(), "503".to_string())]);
+    /// ```
+    pub fn with_field<K: ToString, V: ToString>(mut self, key: K, value: V) -> Fail {
+        self.meta_mut()
+            .fields
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns key-value fields attached to this layer.
+    pub fn fields(&self) -> &[(String, String)] {
+        self.inner.meta.as_deref().map_or(&[], |meta| &meta.fields)
+    }
+
+    /// Adds higher level message to this `Fail`.
+    ///
+    /// If this `Fail` has no message (e.g. created by `From`), the message is set to this layer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low level").context("high level");
+    /// assert_eq!(fail.to_string(), "high level: low level");
+    /// ```
+    #[cold]
+    #[track_caller]
+    pub fn context<S: ToString>(mut self, msg: S) -> Fail {
+        if self.inner.msg.is_none() {
+            self.inner.msg = Some(msg.to_string().into());
+            if self.inner.location.is_none() {
+                self = self.at_caller();
+            }
+            self
+        } else {
+            Fail::from_raw(Some(msg.to_string().into()), Some(FailCause::Fail(self))).at_caller()
+        }
+    }
+
+    /// Sets the source location of this layer, overriding the captured one.
+    ///
+    /// This is useful for code generators to attribute errors to the user's source.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("invalid field").with_location("schema.toml", 3, 7);
+    /// assert_eq!(fail.location().unwrap().to_string(), "schema.toml:3:7");
+    /// ```
+    pub fn with_location(mut self, file: &'static str, line: u32, column: u32) -> Fail {
+        self.inner.location = Some(Location { file, line, column });
+        self
+    }
+
+    /// Returns the source location where this layer was created.
+    ///
+    /// The location is captured by `new`, `with_cause`, `context` and conversion via `?`.
+    pub fn location(&self) -> Option<Location> {
+        self.inner.location
+    }
+
+    /// Returns `Display` that shows the chain from the deepest cause, like `low level message: high level message`.
+    ///
+    /// The alternate form (`{:#}`) shows one message per line.
+    pub fn display_reverse(&self) -> impl fmt::Display + '_ {
+        DisplayReverse(self)
+    }
+
+    /// Returns `Display` that prefixes each message with the source location of its layer, like `[src/main.rs:10] failed open`.
+    ///
+    /// Layers without location are shown as is. The alternate form (`{:#}`) shows one message per line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low").with_location("src/main.rs", 10, 5);
+    /// assert_eq!(fail.display_with_location().to_string(), "[src/main.rs:10] low");
+    /// ```
+    pub fn display_with_location(&self) -> impl fmt::Display + '_ {
+        DisplayWithLocation(self)
+    }
+
+    /// Returns `Display` that shows the chain as a Markdown list, with each cause nested under its parent.
+    ///
+    /// Notes are listed after the chain under a `**Notes:**` heading.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low").context("high");
+    /// assert_eq!(fail.display_markdown().to_string(), "- high\n  - low");
+    /// ```
+    pub fn display_markdown(&self) -> impl fmt::Display + '_ {
+        DisplayMarkdown(self)
+    }
+
+    /// Renders the chain joined by `sep` instead of `": "`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low level").context("high level");
+    /// assert_eq!(fail.join_messages(" | "), "high level | low level");
+    /// ```
+    pub fn join_messages(&self, sep: &str) -> String {
+        Joined { fail: self, sep }.to_string()
+    }
+
+    /// Writes the chain into `w` layer by layer, without building the whole string.
+    ///
+    /// This writes the same as single line `Display`, including the separator set by `with_separator`.
+    pub fn write_chain<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        write!(w, "{}", Joined::single_line(self))
+    }
+
+    /// Returns an iterator rendering each layer lazily, from the outermost.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low level").context("high level");
+    /// let layers: Vec<String> = fail.chain_strings().collect();
+    /// assert_eq!(layers, vec!["high level", "low level"]);
+    /// ```
+    pub fn chain_strings(&self) -> impl Iterator<Item = String> + '_ {
+        self.links()
+            .filter(|link| !matches!(link, Link::Message(msg) if msg.is_empty()))
+            .map(|link| link.to_string())
+    }
+
+    /// Returns `Display` that shows the chain in single line, with control characters escaped like `\n` or `\x1b`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("unexpected \"a\nb\"").context("failed parse");
+    /// assert_eq!(
+    ///     fail.display_escaped().to_string(),
+    ///     "failed parse: unexpected \"a\\nb\""
+    /// );
+    /// ```
+    pub fn display_escaped(&self) -> impl fmt::Display + '_ {
+        DisplayEscaped(self)
+    }
+
+    /// Returns multi-section report for showing to users, such as in CLI.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("no such file")
+    ///     .context("failed open")
+    ///     .context("failed load")
+    ///     .with_location("src/main.rs", 10, 5);
+    /// assert_eq!(
+    ///     fail.report().to_string(),
+    ///     "failed load\n    at src/main.rs:10:5\n\nCaused by:\n    0: failed open\n    1: no such file"
+    /// );
+    /// ```
+    pub fn report(&self) -> Report<'_> {
+        Report { fail: self }
+    }
+
+    /// Returns new `Fail` that has at most `n` messages of this chain.
+    ///
+    /// If messages are omitted, the last one is a marker like `... (8 more)`, which is counted in `n`.
+    /// Foreign errors are converted into messages, and other properties are not copied.
+    pub fn truncate_chain(&self, n: usize) -> Fail {
+        let mut msgs: Vec<String> = self.links().map(|link| link.to_string()).collect();
+        let len = msgs.len();
+        if len > n {
+            let kept = n.saturating_sub(1);
+            msgs.truncate(kept);
+            if n > 0 {
+                msgs.push(format!("... ({} more)", len - kept));
+            }
+        }
+        Fail::from_messages(msgs)
+    }
+
+    /// Returns new `Fail` whose messages are prefixed with `prefix` and `": "`.
+    ///
+    /// Foreign errors are converted into prefixed messages, and other properties are not copied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low").context("high").prefix_all("sync");
+    /// assert_eq!(fail.to_string(), "sync: high: sync: low");
+    /// ```
+    pub fn prefix_all<S: ToString>(&self, prefix: S) -> Fail {
+        let prefix = prefix.to_string();
+        Fail::from_messages(
+            self.links()
+                .map(|link| format!("{}: {}", prefix, link))
+                .collect(),
+        )
+    }
+
+    /// Returns a message-only copy of the chain, where each layer longer than `max` bytes is cut and ends with `…`.
+    ///
+    /// The cut is made at a char boundary, so the kept part may be shorter than `max`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("0123456789").context("failed parse");
+    /// assert_eq!(fail.truncate_messages(6).to_string(), "failed…: 012345…");
+    /// ```
+    pub fn truncate_messages(&self, max: usize) -> Fail {
+        Fail::from_messages(
+            self.links()
+                .map(|link| truncate_message(link.to_string(), max))
+                .collect(),
+        )
+    }
+
+    /// Returns a stable hash of the chain, for grouping occurrences of the same failure.
+    ///
+    /// Numbers, including hexadecimal addresses like `0x7ffd`, are ignored, so dynamic details don't change the result.
+    /// Use `fingerprint_with` to normalize messages in another way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let a = Fail::new("timeout after 30s").context("failed request 1");
+    /// let b = Fail::new("timeout after 60s").context("failed request 2");
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint_with(normalize_numbers)
+    }
+
+    /// Same as `fingerprint`, but each message and foreign error's `Display` is normalized by `normalize`.
+    pub fn fingerprint_with<F: Fn(&str) -> String>(&self, normalize: F) -> u64 {
+        // 64-bit FNV-1a, which is stable across builds unlike `DefaultHasher`.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for &b in bytes {
+                hash ^= u64::from(b);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+
+        for (tag, text) in self.templates(normalize) {
+            write(&[tag]);
+            write(text.as_bytes());
+            write(&[0xff]);
+        }
+        hash
+    }
+
+    /// Returns `true` if both chains have the same shape and the same messages, ignoring numbers and absolute paths.
+    ///
+    /// It's intended for snapshot tests, where line numbers or temporary directories differ between runs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let a = Fail::new("invalid token at line 3").context("failed read /tmp/a1b2/config.toml");
+    /// let b = Fail::new("invalid token at line 17").context("failed read /var/tmp/x/config.toml");
+    /// assert!(a.structural_eq(&b));
+    /// ```
+    pub fn structural_eq(&self, other: &Fail) -> bool {
+        let normalize = |s: &str| normalize_numbers(&normalize_paths(s));
+        self.templates(normalize).eq(other.templates(normalize))
+    }
+
+    /// Returns each non-empty link with a tag of its kind, normalized by `normalize`.
+    fn templates<'a, F: 'a + Fn(&str) -> String>(
+        &'a self,
+        normalize: F,
+    ) -> impl Iterator<Item = (u8, String)> + 'a {
+        self.links().filter_map(move |link| {
+            let tag = match link {
+                Link::Message("") => return None,
+                Link::Message(_) => b'm',
+                Link::Error(_) => b'e',
+                Link::Multiple(_) => b'*',
+            };
+            Some((tag, normalize(&link.to_string())))
+        })
+    }
+
+    /// Rewrites every message in the chain, including aggregated `Fail`s, by `f`.
+    ///
+    /// Foreign errors and other properties are kept as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low").context("high").map_messages(|msg| msg.to_uppercase());
+    /// assert_eq!(fail.to_string(), "HIGH: LOW");
+    /// ```
+    pub fn map_messages<F: Fn(&str) -> String>(mut self, f: F) -> Fail {
+        let mut stack = vec![&mut self];
+        while let Some(fail) = stack.pop() {
+            let inner = &mut *fail.inner;
+            if let Some(msg) = &mut inner.msg {
+                *msg = Cow::Owned(f(msg));
+            }
+            match &mut inner.cause {
+                Some(FailCause::Fail(cause)) => stack.push(cause),
+                Some(FailCause::Multiple(fails)) => stack.extend(fails.iter_mut()),
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Converts every foreign error in the chain, including aggregated `Fail`s, into a message.
+    ///
+    /// The result contains no boxed errors, so `Display` is unchanged but downcasting no longer works.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::from(io::Error::new(io::ErrorKind::NotFound, "no such file")).context("failed open");
+    /// let fail = fail.into_message_chain();
+    /// assert_eq!(fail.to_string(), "failed open: no such file");
+    /// assert!(fail.downcast_ref::<io::Error>().is_none());
+    /// ```
+    pub fn into_message_chain(mut self) -> Fail {
+        let mut stack = vec![&mut self];
+        while let Some(fail) = stack.pop() {
+            let inner = &mut *fail.inner;
+            if let Some(FailCause::Error(e)) = &inner.cause {
+                let msg = e.to_string();
+                if let Some(meta) = &mut inner.meta {
+                    meta.io_kind = None;
+                }
+                if inner.msg.is_none() {
+                    inner.msg = Some(Cow::Owned(msg));
+                    inner.cause = None;
+                } else {
+                    inner.cause = Some(FailCause::Fail(Fail::from_parts(Some(msg), None)));
+                }
+            }
+            match &mut inner.cause {
+                Some(FailCause::Fail(cause)) => stack.push(cause),
+                Some(FailCause::Multiple(fails)) => stack.extend(fails.iter_mut()),
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Aggregates this `Fail` and `other`, in this order, like collecting them into `Fail`.
+    ///
+    /// If either side is already a bare aggregate, its `Fail`s are merged instead of being nested.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("primary failed").combine(Fail::new("fallback failed"));
+    /// assert_eq!(
+    ///     fail.to_string(),
+    ///     "2 errors occurred:\n1. primary failed\n2. fallback failed"
+    /// );
+    /// ```
+    pub fn combine(self, other: Fail) -> Fail {
+        let mut fails = self.into_aggregated();
+        fails.extend(other.into_aggregated());
+        Fail::from_raw(None, Some(FailCause::Multiple(fails)))
+    }
+
+    fn into_aggregated(mut self) -> Vec<Fail> {
+        let inner = &mut *self.inner;
+        if inner.msg.is_none() && inner.meta.is_none() {
+            if let Some(FailCause::Multiple(fails)) = &mut inner.cause {
+                return std::mem::take(fails);
+            }
+        }
+        vec![self]
+    }
+
+    /// Renders the chain once and returns `FrozenFail` that shows the cached string.
+    ///
+    /// It's useful for long-lived failures that are displayed many times.
+    pub fn freeze(self) -> FrozenFail {
+        let rendered = Joined::single_line(&self).to_string();
+        FrozenFail {
+            fail: self,
+            rendered,
+        }
+    }
+
+    /// Builds a chain of message-only layers, from the outermost.
+    fn from_messages(msgs: Vec<String>) -> Fail {
+        let mut fail = None;
+        for msg in msgs.into_iter().rev() {
+            fail = Some(Fail::from_parts(Some(msg), fail));
+        }
+        fail.unwrap_or_default()
+    }
+
+    /// Counts foreign errors of type `T` in the whole chain, including aggregated `Fail`s.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::Fail;
+    ///
+    /// let fails = vec![
+    ///     Fail::from(io::Error::from(io::ErrorKind::NotFound)),
+    ///     Fail::from("x".parse::<i32>().unwrap_err()),
+    ///     Fail::from(io::Error::from(io::ErrorKind::PermissionDenied)),
+    /// ];
+    /// let fail: Fail = fails.into_iter().collect();
+    ///
+    /// assert_eq!(fail.count_causes_of::<io::Error>(), 2);
+    /// ```
+    pub fn count_causes_of<T: 'static + error::Error>(&self) -> usize {
+        self.walk()
+            .filter(|fail| match &fail.inner.cause {
+                Some(FailCause::Error(e)) => e.is::<T>(),
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Calls `f` on each layer of the chain from the outermost, including layers of aggregated `Fail`s.
+    ///
+    /// Aggregated `Fail`s are visited depth-first, in order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::new("low").context("high");
+    /// let mut count = 0;
+    /// fail.visit(|_| count += 1);
+    /// assert_eq!(count, 2);
+    /// ```
+    pub fn visit<F: FnMut(&Fail)>(&self, f: F) {
+        self.walk().for_each(f);
+    }
+
+    /// Calls `f` on each foreign error held in the chain, in the same order as `visit`.
+    ///
+    /// Unlike `iter_errors`, their `source()` chains are not visited.
+    pub fn visit_errors<F: FnMut(&(dyn 'static + error::Error))>(&self, mut f: F) {
+        self.visit(|fail| {
+            if let Some(FailCause::Error(e)) = &fail.inner.cause {
+                f(e.as_ref());
+            }
+        });
+    }
+
+    /// Returns foreign errors in the whole chain, including aggregated `Fail`s.
+    ///
+    /// Each foreign error is followed by its `source()` chain.
+    pub fn iter_errors(&self) -> impl Iterator<Item = &(dyn 'static + error::Error)> {
+        self.walk()
+            .filter_map(|fail| match &fail.inner.cause {
+                Some(FailCause::Error(e)) => Some(e.as_ref() as &(dyn 'static + error::Error)),
+                _ => None,
+            })
+            .flat_map(|e| std::iter::successors(Some(e), |e| e.source()))
+    }
+
+    /// Takes the foreign error at the end of the chain by value, if it is of type `E`.
+    ///
+    /// Otherwise, this `Fail` is returned unchanged.
+    /// Unlike `downcast_ref`, only the deepest cause is checked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::{Fail, FailExt};
+    ///
+    /// let res: Result<(), io::Error> = Err(io::ErrorKind::NotFound.into());
+    /// let fail = res.context("failed open").unwrap_err();
+    /// let err: io::Error = fail.downcast().unwrap();
+    /// assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    /// ```
+    pub fn downcast<E: 'static + error::Error>(mut self) -> Result<E, Fail> {
+        if !self.leaf_error().is_some_and(|e| e.is::<E>()) {
+            return Err(self);
+        }
+
+        match self.deepest_mut().inner.cause.take() {
+            Some(FailCause::Error(e)) => Ok(*e.downcast::<E>().expect("type is checked")),
+            _ => unreachable!("leaf_error() is the cause of the deepest layer"),
+        }
+    }
+
+    /// Returns the first foreign error of type `E` in the chain, found in the order of `iter_errors`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::{Fail, FailExt};
+    ///
+    /// let res: Result<(), io::Error> = Err(io::ErrorKind::NotFound.into());
+    /// let fail = res.context("failed open").unwrap_err();
+    /// assert_eq!(fail.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+    /// ```
+    pub fn downcast_ref<E: 'static + error::Error>(&self) -> Option<&E> {
+        self.iter_errors().find_map(|e| e.downcast_ref::<E>())
+    }
+
+    /// Returns the `ErrorKind` of the first `io::Error` in the chain.
+    ///
+    /// The kind is recorded when `io::Error` is attached by `From`, `with_cause` or `caused_by`, so this needs no downcast in that case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    ///
+    /// use tiny_fail::Fail;
+    ///
+    /// let fail = Fail::from(io::Error::from(io::ErrorKind::NotFound)).context("failed open");
+    /// assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+    /// ```
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        // Layer by layer, so that this agrees with `downcast_ref`.
+        self.walk().find_map(|fail| {
+            let recorded = fail.inner.meta.as_deref().and_then(|meta| meta.io_kind);
+            recorded.or_else(|| match &fail.inner.cause {
+                Some(FailCause::Error(e)) => {
+                    let e: &(dyn 'static + error::Error) = e.as_ref();
+                    std::iter::successors(Some(e), |e| e.source())
+                        .find_map(|e| e.downcast_ref::<io::Error>())
+                        .map(io::Error::kind)
+                }
+                _ => None,
+            })
+        })
+    }
+
+    fn links(&self) -> impl Iterator<Item = Link<'_>> {
+        self.layers().flat_map(|fail| {
+            let msg = fail.inner.msg.as_deref().map(Link::Message);
+            let err = match &fail.inner.cause {
+                Some(FailCause::Error(e)) => Some(Link::Error(e.as_ref())),
+                Some(FailCause::Multiple(fails)) => Some(Link::Multiple(fails)),
+                _ => None,
+            };
+            msg.into_iter().chain(err)
+        })
+    }
+
+    /// Walks all layers including aggregated `Fail`s, in depth-first order.
+    fn walk(&self) -> impl Iterator<Item = &Fail> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let fail = stack.pop()?;
+            match &fail.inner.cause {
+                Some(FailCause::Fail(inner)) => stack.push(inner),
+                Some(FailCause::Multiple(fails)) => stack.extend(fails.iter().rev()),
+                _ => {}
+            }
+            Some(fail)
+        })
+    }
+
+    fn leaf_error(&self) -> Option<&(dyn 'static + error::Error)> {
+        match &self.layers().last()?.inner.cause {
+            Some(FailCause::Error(e)) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+
+    fn layers(&self) -> impl Iterator<Item = &Fail> {
+        std::iter::successors(Some(self), |fail| match &fail.inner.cause {
+            Some(FailCause::Fail(inner)) => Some(inner),
+            _ => None,
+        })
+    }
+
+    fn meta_mut(&mut self) -> &mut Meta {
+        self.inner.meta.get_or_insert_with(Box::default)
+    }
+}
+
+fn truncate_message(mut msg: String, max: usize) -> String {
+    if msg.len() > max {
+        let mut end = max;
+        while !msg.is_char_boundary(end) {
+            end -= 1;
+        }
+        msg.truncate(end);
+        msg.push('…');
+    }
+    msg
+}
+
+/// Replaces each number in `s`, including hexadecimal like `0x7ffd`, with `#`.
+fn normalize_numbers(s: &str) -> String {
+    let mut normalized = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii_digit() {
+            normalized.push(c);
+            continue;
+        }
+
+        let hex = c == '0' && chars.peek() == Some(&'x');
+        if hex {
+            chars.next();
+        }
+        while chars
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || (hex && c.is_ascii_hexdigit()))
+        {
+            chars.next();
+        }
+        normalized.push('#');
+    }
+    normalized
+}
+
+/// Replaces each absolute path in `s`, like `/tmp/a` or `C:\\tmp\\a`, with `<path>`.
+fn normalize_paths(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut normalized = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        let at_boundary = i == 0 || b" \t\n'\"`([=".contains(&bytes[i - 1]);
+        let is_path = bytes[i] == b'/'
+            || (bytes[i].is_ascii_alphabetic() && bytes[i + 1..].starts_with(b":\\"));
+        if at_boundary && is_path {
+            i += s[i..]
+                .find(|c: char| c.is_whitespace() || "'\"`),]".contains(c))
+                .unwrap_or(s.len() - i);
+            normalized.push_str("<path>");
+        } else {
+            let c = s[i..].chars().next().unwrap();
+            normalized.push(c);
+            i += c.len_utf8();
+        }
+    }
+    normalized
+}
+
+const POISONED_MSG: &str = "lock poisoned";
+
+const VERBOSE_ENV: &str = "TINY_FAIL_VERBOSE";
+
+fn verbose() -> bool {
+    // Unit tests expect the default format whatever the environment is.
+    // `tests/verbose_env.rs` covers the variable.
+    if cfg!(test) {
+        return false;
+    }
+    static VERBOSE: OnceLock<bool> = OnceLock::new();
+    *VERBOSE.get_or_init(verbose_from_env)
+}
+
+fn verbose_from_env() -> bool {
+    env::var_os(VERBOSE_ENV).as_deref() == Some(OsStr::new("1"))
+}
+
+/// A message or a foreign error in a chain.
+enum Link<'a> {
+    Message(&'a str),
+    Error(&'a (dyn 'static + error::Error)),
+    Multiple(&'a [Fail]),
+}
+
+impl<'a> fmt::Display for Link<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Link::Message(msg) => f.write_str(msg),
+            Link::Error(e) => e.fmt(f),
+            Link::Multiple(fails) => fmt_multiple(fails, f),
+        }
+    }
+}
+
+struct DisplayReverse<'a>(&'a Fail);
+
+impl<'a> fmt::Display for DisplayReverse<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sep = if f.alternate() { "\n" } else { ": " };
+
+        let links: Vec<Link> = self.0.links().collect();
+        for (i, link) in links.iter().rev().enumerate() {
+            if i > 0 {
+                f.write_str(sep)?;
+            }
+            link.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+struct DisplayWithLocation<'a>(&'a Fail);
+
+impl<'a> fmt::Display for DisplayWithLocation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.renders_empty() {
+            return f.write_str(EMPTY_PLACEHOLDER);
+        }
+
+        let sep = if f.alternate() { "\ncaused by: " } else { ": " };
+
+        let mut need_sep = false;
+        for fail in self.0.layers() {
+            let msg = fail
+                .inner
+                .msg
+                .as_deref()
+                .filter(|msg| !msg.is_empty())
+                .map(Link::Message);
+            let err = match &fail.inner.cause {
+                Some(FailCause::Error(e)) => Some(Link::Error(e.as_ref())),
+                Some(FailCause::Multiple(fails)) => Some(Link::Multiple(fails)),
+                _ => None,
+            };
+
+            // The location is shown once per layer, before its first item.
+            let mut location = fail.inner.location;
+            for link in msg.into_iter().chain(err) {
+                if need_sep {
+                    f.write_str(sep)?;
+                }
+                if let Some(loc) = location.take() {
+                    write!(f, "[{}:{}] ", loc.file(), loc.line())?;
+                }
+                link.fmt(f)?;
+                need_sep = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DisplayMarkdown<'a>(&'a Fail);
+
+impl<'a> fmt::Display for DisplayMarkdown<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.renders_empty() {
+            return write!(f, "- {}", EMPTY_PLACEHOLDER);
+        }
+
+        let links = self
+            .0
+            .links()
+            .filter(|link| !matches!(link, Link::Message("")));
+        for (depth, link) in links.enumerate() {
+            if depth > 0 {
+                f.write_str("\n")?;
+            }
+            let indent = "  ".repeat(depth);
+            // Continuation lines, like ones of aggregated `Fail`s, stay inside the item.
+            let text = link.to_string().replace('\n', &format!("\n{}  ", indent));
+            write!(f, "{}- {}", indent, text)?;
+        }
+
+        let mut notes = self.0.notes().peekable();
+        if notes.peek().is_some() {
+            f.write_str("\n\n**Notes:**\n")?;
+            for note in notes {
+                write!(f, "\n- {}", note)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders the chain joined by `sep`, regardless of formatter flags.
+struct Joined<'a> {
+    fail: &'a Fail,
+    sep: &'a str,
+}
+
+impl<'a> Joined<'a> {
+    /// Joins the chain like single line `Display`, regardless of `TINY_FAIL_VERBOSE`.
+    fn single_line(fail: &'a Fail) -> Joined<'a> {
+        Joined {
+            fail,
+            sep: fail.display_separator(false),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Joined<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fail.renders_empty() {
+            return f.write_str(EMPTY_PLACEHOLDER);
+        }
+        self.fail.fmt_chain(f, self.sep)
+    }
+}
+
+struct DisplayEscaped<'a>(&'a Fail);
+
+impl<'a> fmt::Display for DisplayEscaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rendered = Joined::single_line(self.0).to_string();
+
+        for c in rendered.chars() {
+            match c {
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() && (c as u32) < 0x100 => write!(f, "\\x{:02x}", c as u32)?,
+                c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                c => fmt::Write::write_char(f, c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`Fail`](struct.Fail.html) with cached rendering, returned by [`Fail::freeze`](struct.Fail.html#method.freeze).
+///
+/// `Display` shows the cached string. The alternate form (`{:#}`) is rendered each time.
+#[derive(Debug)]
+pub struct FrozenFail {
+    fail: Fail,
+    rendered: String,
+}
+
+impl FrozenFail {
+    /// Returns the cached string.
+    pub fn as_str(&self) -> &str {
+        &self.rendered
+    }
+
+    /// Returns the frozen `Fail`.
+    pub fn as_fail(&self) -> &Fail {
+        &self.fail
+    }
+
+    /// Unfreezes into `Fail`.
+    pub fn into_fail(self) -> Fail {
+        self.fail
+    }
+}
+
+impl fmt::Display for FrozenFail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            fmt::Display::fmt(&self.fail, f)
+        } else {
+            f.pad(&self.rendered)
+        }
+    }
+}
+
+/// A report of [`Fail`](struct.Fail.html) returned by [`Fail::report`](struct.Fail.html#method.report).
+///
+/// It shows the outermost message first, followed by `Caused by:` section listing each cause.
+#[derive(Debug, Clone, Copy)]
+pub struct Report<'a> {
+    fail: &'a Fail,
+}
+
+impl<'a> fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut links = self
+            .fail
+            .links()
+            .filter(|link| !matches!(link, Link::Message(msg) if msg.is_empty()));
+
+        match links.next() {
+            Some(summary) => write!(f, "{}", summary)?,
+            None => f.write_str(EMPTY_PLACEHOLDER)?,
+        }
+        if let Some(location) = self.fail.location() {
+            write!(f, "\n    at {}", location)?;
+        }
+
+        let causes: Vec<Link> = links.collect();
+        if !causes.is_empty() {
+            f.write_str("\n\nCaused by:")?;
+        }
+        for (i, cause) in causes.iter().enumerate() {
+            let prefix = if causes.len() > 1 {
+                format!("    {}: ", i)
+            } else {
+                "    ".to_string()
+            };
+            for (j, line) in cause.to_string().lines().enumerate() {
+                if j == 0 {
+                    write!(f, "\n{}{}", prefix, line)?;
+                } else {
+                    write!(f, "\n{:width$}{}", "", line, width = prefix.len())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A builder of [`Fail`](struct.Fail.html) that has many optional properties.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::FailBuilder;
+///
+/// let fail = FailBuilder::new()
+///     .message("failed read config")
+///     .str_code("E_CONFIG")
+///     .exit_code(2)
+///     .cause(std::io::Error::from(std::io::ErrorKind::NotFound))
+///     .build();
+///
+/// assert_eq!(fail.exit_code(), Some(2));
+/// ```
+#[derive(Debug)]
+pub struct FailBuilder {
+    fail: Fail,
+}
+
+impl FailBuilder {
+    /// Create new empty builder.
+    pub fn new() -> FailBuilder {
+        FailBuilder {
+            fail: Fail::from_raw(None, None),
+        }
+    }
+
+    /// Sets the message.
+    pub fn message<S: ToString>(mut self, msg: S) -> FailBuilder {
+        self.fail.inner.msg = Some(msg.to_string().into());
+        self
+    }
+
+    /// Sets the string error code. See [`Fail::with_str_code`](struct.Fail.html#method.with_str_code).
+    pub fn str_code<S: Into<String>>(mut self, code: S) -> FailBuilder {
+        self.fail = self.fail.with_str_code(code);
+        self
+    }
+
+    /// Sets the exit code. See [`Fail::with_exit_code`](struct.Fail.html#method.with_exit_code).
+    pub fn exit_code(mut self, code: i32) -> FailBuilder {
+        self.fail = self.fail.with_exit_code(code);
+        self
+    }
+
+    /// Sets the category. See [`Fail::with_category`](struct.Fail.html#method.with_category).
+    pub fn category(mut self, category: Category) -> FailBuilder {
+        self.fail = self.fail.with_category(category);
+        self
+    }
+
+    /// Sets the cause.
+    pub fn cause<E: 'static + Send + Sync + error::Error>(mut self, err: E) -> FailBuilder {
+        self.fail = self.fail.caused_by(err);
+        self
+    }
+
+    /// Builds the `Fail`.
+    pub fn build(self) -> Fail {
+        self.fail
+    }
+}
+
+impl Default for FailBuilder {
+    fn default() -> FailBuilder {
+        FailBuilder::new()
+    }
+}
+
+/// Creates an empty `Fail`, which has neither message nor cause.
+///
+/// An empty `Fail` is a placeholder. It should be populated (e.g. by `context`) before use.
+/// It is displayed as `unknown error`.
+impl Default for Fail {
+    fn default() -> Fail {
+        Fail::from_raw(None, None)
+    }
+}
+
+impl Drop for Fail {
+    fn drop(&mut self) {
+        // Unlink nested layers one at a time so deep chains don't overflow the stack.
+        let mut next = self.inner.cause.take();
+        while let Some(FailCause::Fail(mut fail)) = next {
+            next = fail.inner.cause.take();
+        }
+    }
+}
+
+/// Layers are listed flat from the outermost, so deep chains don't grow the stack.
+impl fmt::Debug for Fail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fail")
+            .field("layers", &DebugLayers(self))
+            .finish()
+    }
+}
+
+struct DebugLayers<'a>(&'a Fail);
+
+impl<'a> fmt::Debug for DebugLayers<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.layers().map(DebugLayer))
+            .finish()
+    }
+}
+
+/// A layer without its nested `Fail` cause.
+struct DebugLayer<'a>(&'a Fail);
+
+impl<'a> fmt::Debug for DebugLayer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inner = &self.0.inner;
+        let mut d = f.debug_struct("Layer");
+        d.field("msg", &inner.msg)
+            .field("meta", &inner.meta)
+            .field("location", &inner.location);
+        #[cfg(feature = "spantrace")]
+        d.field("span_trace", &inner.span_trace);
+        #[cfg(feature = "timestamp")]
+        d.field("timestamp", &inner.timestamp);
+        match &inner.cause {
+            Some(FailCause::Error(e)) => d.field("error", e),
+            Some(FailCause::Multiple(fails)) => d.field("multiple", fails),
+            Some(FailCause::Fail(_)) | None => &mut d,
+        };
+        d.finish()
+    }
+}
+
+/// Width, fill, alignment and precision of the formatter apply to the whole rendered chain.
+impl fmt::Display for Fail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.width().is_some() || f.precision().is_some() {
+            let rendered = if f.alternate() {
+                format!("{:#}", Unpadded(self))
+            } else {
+                Unpadded(self).to_string()
+            };
+            return f.pad(&rendered);
+        }
+
+        Unpadded(self).fmt(f)
+    }
+}
+
+/// Displayed instead of a chain with no visible content.
+const EMPTY_PLACEHOLDER: &str = "unknown error";
+
+/// Renders `Fail` ignoring width and precision of the formatter.
+struct Unpadded<'a>(&'a Fail);
+
+impl<'a> fmt::Display for Unpadded<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fail = self.0;
+        if fail.renders_empty() {
+            return f.write_str(EMPTY_PLACEHOLDER);
+        }
+
+        let sep = fail.display_separator(f.alternate() || verbose());
+        fail.fmt_chain(f, sep)?;
+
+        if f.alternate() {
+            if let Some(suggestion) = fail.suggestion() {
+                write!(f, "\nhelp: did you mean '{}'?", suggestion)?;
+            }
+        }
+
+        #[cfg(feature = "spantrace")]
+        {
+            if f.alternate() {
+                if let Some(span_trace) = fail.span_trace() {
+                    write!(f, "\n\nspan trace:\n{}", span_trace)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Fail {
+    /// Returns the separator between messages for `Display`, which is multi-line if `verbose`.
+    fn display_separator(&self, verbose: bool) -> &'static str {
+        if verbose {
+            "\ncaused by: "
+        } else {
+            self.layers()
+                .filter_map(|fail| fail.inner.meta.as_deref())
+                .find_map(|meta| meta.separator)
+                .unwrap_or(": ")
+        }
+    }
+
+    fn fmt_chain(&self, f: &mut fmt::Formatter, sep: &str) -> fmt::Result {
+        // Walk the layers in a loop so deep chains don't grow the stack.
+        let mut need_sep = false;
+        for fail in self.layers() {
+            if let Some(msg) = fail.inner.msg.as_deref().filter(|msg| !msg.is_empty()) {
+                if need_sep {
+                    f.write_str(sep)?;
+                }
+                f.write_str(msg)?;
+                need_sep = true;
+            }
+
+            // The formatter is passed through, so the alternate flag reaches causes.
+            match &fail.inner.cause {
+                Some(FailCause::Error(e)) => {
+                    if need_sep {
+                        f.write_str(sep)?;
+                    }
+                    fmt::Display::fmt(e, f)?;
+                }
+                Some(FailCause::Multiple(fails)) => {
+                    if need_sep {
+                        f.write_str(sep)?;
+                    }
+                    fmt_multiple(fails, f)?;
+                }
+                Some(FailCause::Fail(_)) | None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn fmt_multiple(fails: &[Fail], f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} errors occurred:", fails.len())?;
+    for (i, fail) in fails.iter().enumerate() {
+        let prefix = format!("{}. ", i + 1);
+        let rendered = if f.alternate() {
+            format!("{:#}", fail)
+        } else {
+            Joined::single_line(fail).to_string()
+        };
+
+        for (j, line) in rendered.lines().enumerate() {
+            if j == 0 {
+                write!(f, "\n{}{}", prefix, line)?;
+            } else {
+                write!(f, "\n{:width$}{}", "", line, width = prefix.len())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Aggregates multiple `Fail`s into one.
+///
+/// An empty iterator produces an empty `Fail`, and a single `Fail` is returned as is.
+///
+/// Aggregated `Fail`s are displayed with a count header, followed by each `Fail` on its own numbered line in the order of the iterator.
+/// In the alternate form, the chain of each `Fail` is shown in multiple lines with indentation.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::Fail;
+///
+/// let fails = vec![Fail::new("invalid name"), Fail::new("invalid age")];
+/// let fail: Fail = fails.into_iter().collect();
+///
+/// assert_eq!(
+///     fail.to_string(),
+///     "2 errors occurred:\n1. invalid name\n2. invalid age"
+/// );
+/// ```
+impl FromIterator<Fail> for Fail {
+    fn from_iter<I: IntoIterator<Item = Fail>>(iter: I) -> Fail {
+        let mut fails: Vec<Fail> = iter.into_iter().collect();
+        match fails.len() {
+            0 => Fail::from_raw(None, None),
+            1 => fails.pop().unwrap(),
+            _ => Fail::from_raw(None, Some(FailCause::Multiple(fails))),
+        }
+    }
+}
+
+impl<E: 'static + Send + Sync + error::Error> From<E> for Fail {
+    #[cold]
+    #[track_caller]
+    fn from(err: E) -> Fail {
+        Fail::from_boxed(Box::new(err))
+    }
+}
+
+/// A conversion into `Fail`.
+///
+/// This is implemented for all `std::error::Error` types and `Fail` itself.
+/// Types that do not implement `std::error::Error`, like your own enums, can implement this trait instead.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{Fail, IntoFail};
+///
+/// enum ConfigError {
+///     Missing(&'static str),
+/// }
+///
+/// impl IntoFail for ConfigError {
+///     fn into_fail(self) -> Fail {
+///         match self {
+///             ConfigError::Missing(key) => Fail::new(format!("missing key {:?}", key)),
+///         }
+///     }
+/// }
+///
+/// let fail = ConfigError::Missing("name").into_fail();
+/// assert_eq!(fail.to_string(), "missing key \"name\"");
+/// ```
+pub trait IntoFail {
+    fn into_fail(self) -> Fail;
+}
+
+impl<E: 'static + Send + Sync + error::Error> IntoFail for E {
+    #[track_caller]
+    fn into_fail(self) -> Fail {
+        Fail::from_boxed(Box::new(self))
+    }
+}
+
+impl IntoFail for Fail {
+    fn into_fail(self) -> Fail {
+        self
+    }
+}
+
+/// A wrapper of `Fail` implements `std::error::Error`.
+///
+/// `Display` shows only the outermost message, and `source()` returns the rest of the chain,
+/// so reporters walking `source()` show each message once. Use `as_fail()` to display the whole chain.
+/// Layers without a message are skipped.
+/// Aggregated `Fail`s have no single source, so they are shown in `Display` and `source()` returns `None` for them.
+///
+/// `Error` can be converted from and into `Fail` by `From`.
+/// Converting `Error` into `Fail` unwraps it instead of boxing it as a foreign error.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error as _;
+///
+/// use tiny_fail::{Error, Fail, FailExt};
+///
+/// fn load() -> Result<String, Fail> {
+///     Err(Fail::new("no such file")).context("failed load")
+/// }
+///
+/// fn run() -> Result<(), Error> {
+///     let config = load()?;
+///     if config.is_empty() {
+///         return Err(Fail::new("empty config").into_error());
+///     }
+///     Ok(())
+/// }
+///
+/// let err = run().unwrap_err();
+/// assert_eq!(err.to_string(), "failed load");
+/// assert_eq!(err.source().unwrap().to_string(), "no such file");
+/// assert_eq!(err.as_fail().to_string(), "failed load: no such file");
+/// ```
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct Error(Fail);
+
+impl Error {
+    /// Wraps `Fail`.
+    pub fn from_fail(fail: Fail) -> Error {
+        Error(fail)
+    }
+
+    pub fn as_fail(&self) -> &Fail {
+        &self.0
+    }
+
+    pub fn into_fail(self) -> Fail {
+        self.0
+    }
+
+    fn from_ref(fail: &Fail) -> &Error {
+        // Safety: `Error` is a `repr(transparent)` wrapper of `Fail`.
+        unsafe { &*(fail as *const Fail as *const Error) }
+    }
+
+    /// Returns the outermost layer that has a message, or the one that ends the chain.
+    fn own_layer(&self) -> (&Fail, Option<&str>) {
+        let mut fail = &self.0;
+        loop {
+            let msg = fail.inner.msg.as_deref().filter(|msg| !msg.is_empty());
+            match &fail.inner.cause {
+                Some(FailCause::Fail(inner)) if msg.is_none() => fail = inner,
+                _ => return (fail, msg),
+            }
+        }
+    }
+}
+
+impl From<Fail> for Error {
+    fn from(fail: Fail) -> Error {
+        Error(fail)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (fail, msg) = self.own_layer();
+        match (msg, &fail.inner.cause) {
+            (Some(msg), Some(FailCause::Multiple(fails))) => {
+                f.write_str(msg)?;
+                f.write_str(self.0.display_separator(false))?;
+                fmt_multiple(fails, f)
+            }
+            (Some(msg), _) => f.write_str(msg),
+            (None, Some(FailCause::Error(e))) => fmt::Display::fmt(e, f),
+            (None, Some(FailCause::Multiple(fails))) => fmt_multiple(fails, f),
+            (None, _) => f.write_str(EMPTY_PLACEHOLDER),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn 'static + error::Error)> {
+        let (fail, msg) = self.own_layer();
+        match &fail.inner.cause {
+            // Without a message, this shows the foreign error itself.
+            Some(FailCause::Error(e)) if msg.is_none() => e.source(),
+            Some(FailCause::Error(e)) => Some(e.as_ref()),
+            Some(FailCause::Fail(inner)) => Some(Error::from_ref(inner)),
+            Some(FailCause::Multiple(_)) | None => None,
+        }
+    }
+}
+
+impl AsRef<dyn 'static + error::Error> for Error {
+    fn as_ref(&self) -> &(dyn 'static + error::Error) {
+        self
+    }
+}
+
+/// Calls `f` until it succeeds, retrying while it fails with a transient `Fail`.
+///
+/// It gives up on a permanent failure (see [`Fail::is_transient`](struct.Fail.html#method.is_transient)) or after `max_attempts` calls.
+/// `f` is called at least once.
+/// The last failure is returned with a message like `gave up after 3 attempts`.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::Fail;
+///
+/// let mut count = 0;
+/// let res = tiny_fail::retry(5, || {
+///     count += 1;
+///     if count < 3 {
+///         Err(Fail::new("busy").transient())
+///     } else {
+///         Ok(count)
+///     }
+/// });
+/// assert_eq!(res.unwrap(), 3);
+/// ```
+#[track_caller]
+pub fn retry<T, F: FnMut() -> Result<T, Fail>>(max_attempts: usize, mut f: F) -> Result<T, Fail> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match f() {
+            Ok(v) => return Ok(v),
+            Err(fail) if fail.is_transient() && attempts < max_attempts => {}
+            Err(fail) => {
+                let unit = if attempts == 1 { "attempt" } else { "attempts" };
+                return Err(fail.context(format!("gave up after {} {}", attempts, unit)));
+            }
+        }
+    }
+}
+
+/// A support trait for implementing rich error message.
+///
+/// This trait implemented for `Option` and `Result`.
+/// You can
+///
+/// # Example
+/// ```
+/// use std::fs::File;
+/// use std::io::{BufRead, BufReader};
+///
+/// use tiny_fail::{Fail, FailExt};
+/// #
+/// # if let Err(e) = foo() {
+/// #     eprintln!("{}", e);
+/// # }
+///
+/// fn foo() -> Result<(), Fail> {
+///     let f = File::open("not_exists.txt").context("failed open not_exists.txt")?;
+///     let r = BufReader::new(f);
+///
+///     for line in r.lines() {
+///         let line = line.context("failed read line")?;
+///         let num = line.parse::<f64>().context(format!("failed parse \"{}\" into floating number", line))?;
+///         println!("{:.2}", num);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait FailExt<T> {
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail>;
+
+    /// Same as `context`, but the message is lazily built by `f` only on failure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    ///
+    /// use tiny_fail::{Fail, FailExt};
+    ///
+    /// fn open(path: &str) -> Result<File, Fail> {
+    ///     File::open(path).context_with(|| format!("failed open {}", path))
+    /// }
+    /// # assert!(open("not_exists.txt").is_err());
+    /// ```
+    fn context_with<S: ToString, F: FnOnce() -> S>(self, f: F) -> Result<T, Fail>;
+
+    /// Attaches key-value field to the error. The value is computed by `f` only on failure.
+    ///
+    /// `None` is converted into an empty `Fail`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::FailExt;
+    ///
+    /// let fail = "x".parse::<i32>()
+    ///     .with_field("input", || "x".to_string())
+    ///     .unwrap_err();
+    /// assert_eq!(fail.fields(), &[("input".to_string(), "x".to_string())]);
+    /// ```
+    fn with_field<F: FnOnce() -> String>(self, key: &'static str, f: F) -> Result<T, Fail>;
+
+    /// Same as `context`, but converts the whole chain, including each `source()` of foreign errors, into message layers.
+    ///
+    /// The whole chain becomes visible in `Display`, but the original error can't be downcasted.
+    /// For `Option`, this is the same as `context`.
+    fn context_flatten<S: ToString>(self, msg: S) -> Result<T, Fail>;
+
+    /// Adds message like `failed to {op} {path}` for file operations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fs::File;
+    ///
+    /// use tiny_fail::FailExt;
+    ///
+    /// let fail = File::open("not_exists.txt")
+    ///     .context_path("open", "not_exists.txt")
+    ///     .unwrap_err();
+    /// assert!(fail.to_string().starts_with("failed to open not_exists.txt: "));
+    /// ```
+    #[track_caller]
+    fn context_path<P: AsRef<Path>>(self, op: &str, path: P) -> Result<T, Fail>
+    where
+        Self: Sized,
+    {
+        self.context_with(|| format!("failed to {} {}", op, path.as_ref().display()))
+    }
+
+    /// Adds message like `{msg} failed after 1.23s`, with the time elapsed since `start`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Instant;
+    ///
+    /// use tiny_fail::FailExt;
+    ///
+    /// let start = Instant::now();
+    /// let fail = "x".parse::<i32>().context_timed("parse", start).unwrap_err();
+    /// assert!(fail.to_string().starts_with("parse failed after "));
+    /// ```
+    #[track_caller]
+    fn context_timed<S: ToString>(self, msg: S, start: Instant) -> Result<T, Fail>
+    where
+        Self: Sized,
+    {
+        self.context_with(|| format!("{} failed after {:.2?}", msg.to_string(), start.elapsed()))
+    }
+
+    /// Adds message like `context`, then reports the failure and discards it, for best-effort operations.
+    ///
+    /// The failure is logged at `Error` level with the `log` feature, or printed to stderr otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::FailExt;
+    ///
+    /// let num = "x".parse::<i32>().ok_or_log("failed parse cache");
+    /// assert_eq!(num, None);
+    /// ```
+    #[track_caller]
+    fn ok_or_log<S: ToString>(self, msg: S) -> Option<T>
+    where
+        Self: Sized,
+    {
+        match self.context(msg) {
+            Ok(v) => Some(v),
+            Err(fail) => {
+                #[cfg(feature = "log")]
+                fail.log_error();
+                #[cfg(not(feature = "log"))]
+                eprintln!("{}", fail);
+                None
+            }
+        }
+    }
+
+    /// Same as `context`, but also records the original error's message as the [`SOURCE_FIELD`](constant.SOURCE_FIELD.html) field.
+    ///
+    /// `None` has no original error, so no field is recorded for `Option`.
+    fn context_structured<S: ToString>(self, msg: S) -> Result<T, Fail>;
+
+    /// Calls `f` with the error converted into `Fail`, and passes the result along.
+    ///
+    /// `None` is converted into an empty `Fail`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::{Fail, FailExt};
+    ///
+    /// fn parse(s: &str) -> Result<i32, Fail> {
+    ///     s.parse::<i32>()
+    ///         .inspect_fail(|fail| eprintln!("warning: {}", fail))
+    ///         .context("invalid number")
+    /// }
+    /// # assert!(parse("x").is_err());
+    /// ```
+    fn inspect_fail<F: FnOnce(&Fail)>(self, f: F) -> Result<T, Fail>;
+}
+
+/// Implemented for any error type that can be converted into `Fail`, including `Fail` itself and `std::error::Error` types.
+///
+/// Implement `From<YourError> for Fail` to use `FailExt` with your own error type.
+impl<T, E> FailExt<T> for Result<T, E>
+where
+    Fail: From<E>,
+{
+    #[inline]
+    #[track_caller]
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::from(err).context(msg)),
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    fn context_with<S: ToString, F: FnOnce() -> S>(self, f: F) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::from(err).context(f())),
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    fn with_field<F: FnOnce() -> String>(self, key: &'static str, f: F) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::from(err).with_field(key, f())),
+        }
+    }
+
+    #[track_caller]
+    fn context_flatten<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => {
+                let fail = Fail::from(err);
+                let mut msgs = vec![msg.to_string()];
+                for link in fail.links() {
+                    match link {
+                        Link::Message("") => {}
+                        Link::Error(e) => {
+                            for e in std::iter::successors(Some(e), |e| e.source()) {
+                                // Transparent wrappers repeat the message of their source.
+                                let msg = e.to_string();
+                                if msgs.last() != Some(&msg) {
+                                    msgs.push(msg);
+                                }
+                            }
+                        }
+                        link => msgs.push(link.to_string()),
+                    }
+                }
+                Err(Fail::from_messages(msgs).at_caller())
+            }
+        }
+    }
+
+    #[track_caller]
+    fn context_structured<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => {
+                let fail = Fail::from(err);
+                let source = Joined {
+                    fail: &fail,
+                    sep: ": ",
+                }
+                .to_string();
+                Err(fail.context(msg).with_field(SOURCE_FIELD, source))
+            }
+        }
+    }
+
+    fn inspect_fail<F: FnOnce(&Fail)>(self, f: F) -> Result<T, Fail> {
+        let res = self.map_err(Fail::from);
+        if let Err(fail) = &res {
+            f(fail);
+        }
+        res
+    }
+}
+
+impl<T> FailExt<T> for Option<T> {
+    #[inline]
+    #[track_caller]
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Some(v) => Ok(v),
+            None => Err(Fail::new(msg)),
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    fn context_with<S: ToString, F: FnOnce() -> S>(self, f: F) -> Result<T, Fail> {
+        match self {
+            Some(v) => Ok(v),
+            None => Err(Fail::new(f())),
+        }
+    }
+
+    #[inline]
+    fn with_field<F: FnOnce() -> String>(self, key: &'static str, f: F) -> Result<T, Fail> {
+        match self {
+            Some(v) => Ok(v),
+            None => Err(Fail::default().with_field(key, f())),
+        }
+    }
+
+    #[track_caller]
+    fn context_flatten<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        self.context(msg)
+    }
+
+    #[track_caller]
+    fn context_structured<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        self.context(msg)
+    }
+
+    fn inspect_fail<F: FnOnce(&Fail)>(self, f: F) -> Result<T, Fail> {
+        self.ok_or_else(Fail::default).inspect_fail(f)
+    }
+}
+
+/// A support trait for adding context to `Result` with string errors.
+///
+/// The string error becomes a message-only layer under the context message.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{Fail, MessageExt};
+///
+/// fn parse(s: &str) -> Result<u32, String> {
+///     s.parse().map_err(|_| format!("invalid number \"{}\"", s))
+/// }
+///
+/// let fail = parse("x").context("failed read config").unwrap_err();
+/// assert_eq!(fail.to_string(), "failed read config: invalid number \"x\"");
+/// ```
+pub trait MessageExt<T> {
+    /// Converts the string error into `Fail` and adds message to it.
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail>;
+}
+
+impl<T> MessageExt<T> for Result<T, String> {
+    #[inline]
+    #[track_caller]
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::new(err).context(msg)),
+        }
+    }
+}
+
+impl<T> MessageExt<T> for Result<T, &str> {
+    #[inline]
+    #[track_caller]
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::new(err).context(msg)),
+        }
+    }
+}
+
+/// A support trait for adding context to `Result` with errors that can't be stored, such as borrowing ones.
+///
+/// The error is rendered into a message-only layer eagerly, so it only needs to implement `Display`.
+///
+/// # Example
+///
+/// ```
+/// use std::fmt;
+///
+/// use tiny_fail::DisplayExt;
+///
+/// struct ParseError<'a> {
+///     rest: &'a str,
+/// }
+///
+/// impl<'a> fmt::Display for ParseError<'a> {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "unexpected \"{}\"", self.rest)
+///     }
+/// }
+///
+/// fn parse(input: &str) -> Result<(), ParseError<'_>> {
+///     Err(ParseError { rest: input })
+/// }
+///
+/// let fail = parse("x").context_owned("failed parse").unwrap_err();
+/// assert_eq!(fail.to_string(), "failed parse: unexpected \"x\"");
+/// ```
+pub trait DisplayExt<T> {
+    /// Renders the error into `Fail` and adds message to it.
+    fn context_owned<S: ToString>(self, msg: S) -> Result<T, Fail>;
+}
+
+impl<T, E: fmt::Display> DisplayExt<T> for Result<T, E> {
+    #[inline]
+    #[track_caller]
+    fn context_owned<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::from_display(err).context(msg)),
+        }
+    }
+}
+
+/// A support trait for iterators of `Result<T, Fail>`.
+pub trait FailIterExt<T>: Iterator<Item = Result<T, Fail>> + Sized {
+    /// Collects all items, separating successes from failures instead of stopping at the first failure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tiny_fail::{Fail, FailExt, FailIterExt};
+    ///
+    /// let (nums, fails) = vec!["1", "x", "3"]
+    ///     .into_iter()
+    ///     .map(|s| s.parse::<i32>().context(format!("invalid number {:?}", s)))
+    ///     .collect_fails();
+    ///
+    /// assert_eq!(nums, vec![1, 3]);
+    /// assert_eq!(fails.len(), 1);
+    /// ```
+    fn collect_fails(self) -> (Vec<T>, Vec<Fail>) {
+        let mut oks = Vec::new();
+        let mut fails = Vec::new();
+        for item in self {
+            match item {
+                Ok(v) => oks.push(v),
+                Err(fail) => fails.push(fail),
+            }
+        }
+        (oks, fails)
+    }
+}
+
+impl<T, I: Iterator<Item = Result<T, Fail>>> FailIterExt<T> for I {}
+
+/// A support trait for adding context to futures resolving into `Result`.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{Fail, FailFutureExt};
+///
+/// async fn read_config() -> Result<String, std::io::Error> {
+///     # Ok(String::new())
+///     // ...
+/// }
+///
+/// async fn load() -> Result<String, Fail> {
+///     read_config().context("failed read config").await
+/// }
+/// ```
+pub trait FailFutureExt<T>: Future + Sized {
+    /// Adds message to the error when this future resolves into `Err`.
+    fn context<S: ToString>(self, msg: S) -> ContextFuture<Self, S>;
+}
+
+impl<T, E, F> FailFutureExt<T> for F
+where
+    F: Future<Output = Result<T, E>>,
+    Result<T, E>: FailExt<T>,
+{
+    fn context<S: ToString>(self, msg: S) -> ContextFuture<F, S> {
+        ContextFuture {
+            future: self,
+            msg: Some(msg),
+        }
+    }
+}
+
+/// A future returned by [`FailFutureExt::context`](trait.FailFutureExt.html#tymethod.context).
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ContextFuture<F, S> {
+    future: F,
+    msg: Option<S>,
+}
+
+impl<T, E, F, S> Future for ContextFuture<F, S>
+where
+    F: Future<Output = Result<T, E>>,
+    Result<T, E>: FailExt<T>,
+    S: ToString,
+{
+    type Output = Result<T, Fail>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        // Safety: `future` is structurally pinned and never moved out.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+        match future.poll(cx) {
+            Poll::Ready(res) => {
+                let msg = this
+                    .msg
+                    .take()
+                    .expect("ContextFuture polled after completion");
+                Poll::Ready(res.context(msg))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A support trait for adding context to `Poll<Result<T, E>>` in manual future implementations.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use std::task::Poll;
+///
+/// use tiny_fail::PollExt;
+///
+/// let poll: Poll<Result<(), io::Error>> = Poll::Ready(Err(io::ErrorKind::NotFound.into()));
+/// let poll = poll.context("failed read");
+/// assert!(matches!(poll, Poll::Ready(Err(_))));
+/// ```
+pub trait PollExt<T> {
+    /// Adds message to the error when `Ready(Err(..))`. `Pending` and `Ready(Ok(..))` are passed through.
+    fn context<S: ToString>(self, msg: S) -> Poll<Result<T, Fail>>;
+}
+
+impl<T, E> PollExt<T> for Poll<Result<T, E>>
+where
+    Result<T, E>: FailExt<T>,
+{
+    fn context<S: ToString>(self, msg: S) -> Poll<Result<T, Fail>> {
+        self.map(|res| res.context(msg))
+    }
+}
+
+/// A support trait for adding context to results of locking, such as `Mutex::lock`.
+///
+/// `PoisonError` holds the lock guard, which is usually not `Send` nor `'static`, so it can't be converted by `?` directly.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Mutex;
+///
+/// use tiny_fail::{Fail, PoisonExt};
+///
+/// fn incr(counter: &Mutex<u32>) -> Result<(), Fail> {
+///     let mut count = counter.lock().context("failed lock counter")?;
+///     *count += 1;
+///     Ok(())
+/// }
+/// #
+/// # incr(&Mutex::new(0)).unwrap();
+/// ```
+pub trait PoisonExt<T> {
+    /// Drops the guard and adds message to `lock poisoned` error.
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail>;
+}
+
+impl<T, G> PoisonExt<T> for Result<T, PoisonError<G>> {
+    #[inline]
+    #[track_caller]
+    fn context<S: ToString>(self, msg: S) -> Result<T, Fail> {
+        match self {
+            Ok(v) => Ok(v),
+            Err(err) => Err(Fail::from_poison(err).context(msg)),
+        }
+    }
+}
+
+/// Macro to format and return `Err(Fail::new(..))`.
+///
+/// Arguments format is same as [`std::format!()`](https://doc.rust-lang.org/std/macro.format.html).
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{raise, Fail};
+/// #
+/// # fn main() {
+/// #     let err = foo().unwrap_err();
+/// #     assert_eq!(&format!("{}", err), "Some error caused by 123");
+/// # }
+///
+/// fn foo() -> Result<(), Fail> {
+///     raise!("Some error caused by {}", 123);
+/// }
+/// ```
+#[macro_export]
+macro_rules! raise {
+    ($($arg:tt)*) => {
+        return std::result::Result::Err($crate::Fail::new(std::format!($($arg)*)))
+    };
+}
+
+/// Macro to return `Err(Fail::new(..))` if the condition is not satisfied.
+///
+/// Arguments after the condition are same as [`raise!()`](macro.raise.html).
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{ensure, Fail};
+/// #
+/// # fn main() {
+/// #     assert!(check(1).is_ok());
+/// #     assert_eq!(check(-1).unwrap_err().to_string(), "-1 is negative");
+/// # }
+///
+/// fn check(x: i32) -> Result<(), Fail> {
+///     ensure!(x >= 0, "{} is negative", x);
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::raise!($($arg)+);
+        }
+    };
+}
+
+/// Macro to return `Err(Fail::new(..))` if two expressions are not equal.
+///
+/// It's like `assert_eq!()`, but returns `Fail` instead of panicking.
+/// The message contains both values formatted by `Debug`.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{ensure_eq, Fail};
+/// #
+/// # fn main() {
+/// #     assert!(check_magic(&[0x7f, 0x45]).is_ok());
+/// #     assert_eq!(
+/// #         check_magic(&[0x00, 0x45]).unwrap_err().to_string(),
+/// #         "invalid magic (left: `0`, right: `127`)"
+/// #     );
+/// # }
+///
+/// fn check_magic(data: &[u8]) -> Result<(), Fail> {
+///     ensure_eq!(data[0], 0x7f, "invalid magic");
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::raise!(
+                        "`left == right` failed (left: `{:?}`, right: `{:?}`)",
+                        left,
+                        right
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::raise!(
+                        "{} (left: `{:?}`, right: `{:?}`)",
+                        std::format_args!($($arg)+),
+                        left,
+                        right
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Macro to return `Err(Fail::new(..))` if two expressions are equal.
+///
+/// It's like `assert_ne!()`, but returns `Fail` instead of panicking.
+/// See [`ensure_eq!()`](macro.ensure_eq.html).
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::raise!(
+                        "`left != right` failed (left: `{:?}`, right: `{:?}`)",
+                        left,
+                        right
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::raise!(
+                        "{} (left: `{:?}`, right: `{:?}`)",
+                        std::format_args!($($arg)+),
+                        left,
+                        right
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Asserts that an expression is `Err(Fail)` or `Err(Error)` which contains the substring in some layer.
+///
+/// The substring is searched by [`Fail::contains_message`](struct.Fail.html#method.contains_message).
+/// On failure, it panics with the whole chain.
+/// It's intended for tests of error paths.
+///
+/// # Example
+///
+/// ```
+/// use tiny_fail::{assert_fail, Fail, FailExt};
+///
+/// fn parse(s: &str) -> Result<i32, Fail> {
+///     s.parse::<i32>().context("invalid number")
+/// }
+///
+/// assert_fail!(parse("x"), "invalid number");
+/// ```
+#[macro_export]
+macro_rules! assert_fail {
+    ($expr:expr, $substr:expr $(,)?) => {
+        match $expr {
+            Ok(_) => std::panic!(
+                "assertion failed: `{}` is `Ok`, expected `Err`",
+                std::stringify!($expr)
+            ),
+            Err(fail) => {
+                let fail: $crate::Fail = $crate::IntoFail::into_fail(fail);
+                let substr: &str = &$substr;
+                if !fail.contains_message(substr) {
+                    std::panic!("assertion failed: `{}` doesn't contain {:?}", fail, substr);
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    fn assert_static<T: 'static>() {}
+
+    #[test]
+    fn fail_is_send() {
+        assert_send::<Fail>();
+    }
+
+    #[test]
+    fn fail_is_sync() {
+        assert_sync::<Fail>();
+    }
+
+    #[test]
+    fn error_is_send() {
+        assert_send::<Error>();
+    }
+
+    #[test]
+    fn error_is_sync() {
+        assert_sync::<Error>();
+    }
+
+    #[test]
+    fn fail_and_error_are_static() {
+        assert_static::<Fail>();
+        assert_static::<Error>();
+        assert_static::<FrozenFail>();
+    }
+
+    #[test]
+    fn other_types_are_send_and_sync() {
+        assert_send::<FrozenFail>();
+        assert_sync::<FrozenFail>();
+        assert_send::<FailBuilder>();
+        assert_sync::<FailBuilder>();
+        assert_send::<Report<'_>>();
+        assert_sync::<Report<'_>>();
+        assert_send::<Location>();
+        assert_sync::<Location>();
+        assert_send::<Category>();
+        assert_sync::<Category>();
+    }
+
+    fn io_error() -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no such file")
+    }
+
+    #[test]
+    fn new_static_is_same_as_new() {
+        let fail = Fail::new_static("static message");
+        assert!(matches!(fail.inner.msg, Some(Cow::Borrowed(_))));
+        assert_eq!(fail.to_string(), Fail::new("static message").to_string());
+    }
+
+    #[test]
+    fn caused_by_attaches_error() {
+        let fail = Fail::new("failed open").caused_by(io_error());
+        assert_eq!(fail.to_string(), "failed open: no such file");
+
+        match &fail.inner.cause {
+            Some(FailCause::Error(e)) => {
+                let io_err = e.downcast_ref::<std::io::Error>().unwrap();
+                assert_eq!(io_err.kind(), std::io::ErrorKind::NotFound);
+            }
+            _ => panic!("cause is not an error"),
+        }
+    }
+
+    #[test]
+    fn context_merges_into_empty_message() {
+        let fail = Fail::from(io_error()).context("failed open");
+        assert_eq!(fail.to_string(), "failed open: no such file");
+        assert!(matches!(fail.inner.cause, Some(FailCause::Error(_))));
+    }
+
+    #[test]
+    fn context_nests_fail() {
+        let fail = Fail::new("low level").context("high level");
+        assert_eq!(fail.to_string(), "high level: low level");
+        match &fail.inner.cause {
+            Some(FailCause::Fail(inner)) => assert_eq!(inner.to_string(), "low level"),
+            _ => panic!("cause is not a fail"),
+        }
+    }
+
+    #[test]
+    fn cause_ref_none() {
+        assert!(matches!(Fail::new("x").cause(), CauseRef::None));
+    }
+
+    #[test]
+    fn cause_ref_error() {
+        let fail = Fail::new("x").caused_by(io_error());
+        match fail.cause() {
+            CauseRef::Error(e) => assert!(e.is::<std::io::Error>()),
+            cause => panic!("unexpected cause: {:?}", cause),
+        }
+    }
+
+    #[test]
+    fn cause_ref_fail() {
+        let fail = Fail::new("low").context("high");
+        match fail.cause() {
+            CauseRef::Fail(inner) => assert_eq!(inner.to_string(), "low"),
+            cause => panic!("unexpected cause: {:?}", cause),
+        }
+    }
+
+    #[test]
+    fn message_contains_searches_layers() {
+        let fail = three_layers();
+        assert!(fail.message_contains("load"));
+        assert!(fail.message_contains("open"));
+        assert!(!fail.message_contains("no such"));
+        assert!(!fail.message_contains("save"));
+    }
+
+    #[test]
+    fn chain_contains_searches_leaf() {
+        let fail = three_layers();
+        assert!(fail.chain_contains("load"));
+        assert!(fail.chain_contains("failed open"));
+        assert!(fail.chain_contains("no such file"));
+        assert!(!fail.chain_contains("save"));
+    }
+
+    #[test]
+    fn notes_are_collected_from_chain() {
+        let fail = Fail::new("low")
+            .with_note("low note")
+            .context("high")
+            .with_note("high note 1")
+            .with_note("high note 2");
+
+        let notes: Vec<&str> = fail.notes().collect();
+        assert_eq!(notes, vec!["high note 1", "high note 2", "low note"]);
+        assert_eq!(fail.to_string(), "high: low");
+    }
+
+    #[test]
+    fn error_source_walks_chain() {
+        use std::error::Error as _;
+
+        let err = Error(three_layers());
+
+        let mut visited = vec![err.to_string()];
+        let mut source = err.source();
+        while let Some(e) = source {
+            visited.push(e.to_string());
+            source = e.source();
+        }
+
+        assert_eq!(visited, vec!["failed load", "failed open", "no such file"]);
+    }
+
+    #[test]
+    fn str_code_is_set() {
+        let fail = Fail::new("not found").with_str_code("E_NOT_FOUND");
+        assert_eq!(fail.str_code(), Some("E_NOT_FOUND"));
+    }
+
+    #[test]
+    fn str_code_is_inherited_and_overridden() {
+        let fail = Fail::new("not found")
+            .with_str_code("E_NOT_FOUND")
+            .context("failed load");
+        assert_eq!(fail.str_code(), Some("E_NOT_FOUND"));
+
+        let fail = fail
+            .context("failed request")
+            .with_str_code("E_BAD_REQUEST");
+        assert_eq!(fail.str_code(), Some("E_BAD_REQUEST"));
+    }
+
+    #[test]
+    fn code_is_set_and_inherited() {
+        let fail = Fail::new("no such user").with_code("E_NOT_FOUND");
+        assert_eq!(fail.code(), Some("E_NOT_FOUND"));
+
+        let fail = fail.context("failed get user");
+        assert_eq!(fail.code(), Some("E_NOT_FOUND"));
+        assert_eq!(fail.str_code(), Some("E_NOT_FOUND"));
+
+        let fail = fail.context("failed handle").with_code("E_BAD_REQUEST");
+        assert_eq!(fail.code(), Some("E_BAD_REQUEST"));
+    }
+
+    #[test]
+    fn str_code_is_absent() {
+        assert_eq!(three_layers().str_code(), None);
+    }
+
+    #[test]
+    fn exit_code_is_inherited_and_overridden() {
+        let fail = Fail::new("low").with_exit_code(2).context("high");
+        assert_eq!(fail.exit_code(), Some(2));
+        assert_eq!(fail.with_exit_code(3).exit_code(), Some(3));
+        assert_eq!(Fail::new("x").exit_code(), None);
+    }
+
+    #[test]
+    fn builder_sets_all_properties() {
+        let fail = FailBuilder::new()
+            .message("failed open")
+            .str_code("E_NOT_FOUND")
+            .exit_code(2)
+            .cause(io_error())
+            .build();
+
+        assert_eq!(fail.to_string(), "failed open: no such file");
+        assert_eq!(fail.str_code(), Some("E_NOT_FOUND"));
+        assert_eq!(fail.exit_code(), Some(2));
+        match fail.cause() {
+            CauseRef::Error(e) => assert!(e.is::<std::io::Error>()),
+            cause => panic!("unexpected cause: {:?}", cause),
+        }
+    }
+
+    #[test]
+    fn fail_into_error() {
+        let err = Error::from(three_layers());
+        assert_eq!(err.to_string(), "failed load");
+        assert_eq!(
+            err.as_fail().to_string(),
+            "failed load: failed open: no such file"
+        );
+
+        let err = Error::from_fail(three_layers());
+        assert_eq!(err.to_string(), "failed load");
+    }
+
+    #[test]
+    fn error_skips_layers_without_message() {
+        use std::error::Error as _;
+
+        let err = Error::from(Fail::from(io_error()));
+        assert_eq!(err.to_string(), "no such file");
+        assert!(err.source().is_none());
+
+        let err = Error::from(Fail::from_parts(None, Some(three_layers())));
+        assert_eq!(err.to_string(), "failed load");
+        assert_eq!(err.source().unwrap().to_string(), "failed open");
+
+        let err = Error::from(Fail::new("a").combine(Fail::new("b")).context("failed all"));
+        assert_eq!(
+            err.to_string(),
+            "failed all: 2 errors occurred:\n1. a\n2. b"
+        );
+        assert!(err.source().is_none());
+
+        assert_eq!(Error::from(Fail::new("")).to_string(), EMPTY_PLACEHOLDER);
+    }
+
+    #[test]
+    fn into_error_round_trip() {
+        let err = three_layers().into_error();
+        assert_eq!(err.to_string(), "failed load");
+
+        let fail = err.into_fail();
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+        assert_eq!(fail.inner.msg.as_deref(), Some("failed load"));
+    }
+
+    #[test]
+    fn error_as_ref_dyn_error() {
+        fn takes(e: impl AsRef<dyn error::Error>) -> String {
+            e.as_ref().source().unwrap().to_string()
+        }
+
+        assert_eq!(takes(Error::from_fail(three_layers())), "failed open");
+    }
+
+    #[test]
+    fn error_into_fail_unwraps() {
+        let fail = Fail::from(Error::from_fail(three_layers()));
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+        assert_eq!(fail.inner.msg.as_deref(), Some("failed load"));
+
+        let fail = Err::<(), _>(Error::from_fail(Fail::new("low")))
+            .context("high")
+            .unwrap_err();
+        assert_eq!(fail.to_string(), "high: low");
+        assert!(matches!(fail.cause(), CauseRef::Fail(_)));
+    }
+
+    #[test]
+    fn display_reverse_starts_from_cause() {
+        let fail = three_layers();
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+        assert_eq!(
+            fail.display_reverse().to_string(),
+            "no such file: failed open: failed load"
+        );
+        assert_eq!(
+            format!("{:#}", fail.display_reverse()),
+            "no such file\nfailed open\nfailed load"
+        );
+    }
+
+    fn check_eq(a: &str, b: &str) -> Result<(), Fail> {
+        ensure_eq!(a, b, "mismatch at {}", 3);
+        Ok(())
+    }
+
+    fn check_ne(a: i32, b: i32) -> Result<(), Fail> {
+        ensure_ne!(a, b);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_eq_passes() {
+        assert!(check_eq("a", "a").is_ok());
+    }
+
+    #[test]
+    fn ensure_eq_fails_with_operands() {
+        let fail = check_eq("a", "b").unwrap_err();
+        assert_eq!(
+            fail.to_string(),
+            "mismatch at 3 (left: `\"a\"`, right: `\"b\"`)"
+        );
+    }
+
+    #[test]
+    fn ensure_ne_passes() {
+        assert!(check_ne(1, 2).is_ok());
+    }
+
+    #[test]
+    fn ensure_ne_fails_with_operands() {
+        let fail = check_ne(1, 1).unwrap_err();
+        assert_eq!(
+            fail.to_string(),
+            "`left != right` failed (left: `1`, right: `1`)"
+        );
+    }
+
+    #[test]
+    fn collect_empty_fails() {
+        let fail: Fail = Vec::new().into_iter().collect();
+        assert_eq!(fail.to_string(), "unknown error");
+        assert!(matches!(fail.cause(), CauseRef::None));
+    }
+
+    #[test]
+    fn collect_single_fail() {
+        let fail: Fail = vec![Fail::new("only")].into_iter().collect();
+        assert_eq!(fail.to_string(), "only");
+        assert!(matches!(fail.cause(), CauseRef::None));
+    }
+
+    #[test]
+    fn collect_multiple_fails() {
+        let fail: Fail = vec![Fail::new("first"), three_layers()]
+            .into_iter()
+            .collect();
+        match fail.cause() {
+            CauseRef::Multiple(fails) => assert_eq!(fails.len(), 2),
+            cause => panic!("unexpected cause: {:?}", cause),
+        }
+    }
+
+    #[test]
+    fn display_multiple() {
+        let fail: Fail = vec![Fail::new("first"), three_layers()]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            fail.to_string(),
+            "2 errors occurred:\n\
+             1. first\n\
+             2. failed load: failed open: no such file"
+        );
+    }
+
+    #[test]
+    fn display_multiple_alternate() {
+        let fail: Fail = vec![Fail::new("first"), three_layers()]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            format!("{:#}", fail),
+            "2 errors occurred:\n\
+             1. first\n\
+             2. failed load\n\
+             \x20  caused by: failed open\n\
+             \x20  caused by: no such file"
+        );
+    }
+
+    #[test]
+    fn display_multiple_in_chain() {
+        let fail: Fail = vec![Fail::new("first"), Fail::new("second")]
+            .into_iter()
+            .collect();
+        let fail = fail.context("validation failed");
+        assert_eq!(
+            fail.to_string(),
+            "validation failed: 2 errors occurred:\n1. first\n2. second"
+        );
+    }
+
+    #[test]
+    fn transient_flag_is_inherited_and_overridden() {
+        let fail = Fail::new("low").transient().context("high");
+        assert!(fail.is_transient());
+        assert!(!fail.permanent().is_transient());
+        assert!(!Fail::new("x").is_transient());
+    }
+
+    #[test]
+    fn transient_is_detected_from_io_error() {
+        let timed_out = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        let fail = Err::<(), _>(timed_out)
+            .context("failed connect")
+            .unwrap_err();
+        assert!(fail.is_transient());
+        assert!(!fail.permanent().is_transient());
+
+        assert!(!three_layers().is_transient());
+    }
+
+    #[test]
+    fn truncate_chain_adds_marker() {
+        let mut fail = Fail::new("layer 0");
+        for i in 1..10 {
+            fail = fail.context(format!("layer {}", i));
+        }
+
+        let truncated = fail.truncate_chain(3);
+        assert_eq!(truncated.to_string(), "layer 9: layer 8: ... (8 more)");
+        assert_eq!(truncated.layers().count(), 3);
+
+        assert_eq!(fail.truncate_chain(1).to_string(), "... (10 more)");
+        assert!(fail.truncate_chain(0).is_empty());
+    }
+
+    #[test]
+    fn truncate_chain_keeps_short_chain() {
+        let truncated = three_layers().truncate_chain(3);
+        assert_eq!(
+            truncated.to_string(),
+            "failed load: failed open: no such file"
+        );
+        assert_eq!(truncated.layers().count(), 3);
+    }
+
+    #[test]
+    fn count_causes_of_walks_tree() {
+        let parse_err = "x".parse::<i32>().unwrap_err();
+        let nested: Fail = vec![Fail::from(io_error()), Fail::new("message only")]
+            .into_iter()
+            .collect();
+        let fail: Fail = vec![
+            three_layers(),
+            Fail::from(parse_err).context("failed parse"),
+            nested.context("nested"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(fail.count_causes_of::<io::Error>(), 2);
+        assert_eq!(fail.count_causes_of::<std::num::ParseIntError>(), 1);
+        assert_eq!(fail.count_causes_of::<std::fmt::Error>(), 0);
+        assert_eq!(Fail::new("x").count_causes_of::<io::Error>(), 0);
+    }
+
+    #[test]
+    fn into_fail_converts_error() {
+        let fail = io_error().into_fail();
+        assert_eq!(fail.to_string(), "no such file");
+        assert!(matches!(fail.cause(), CauseRef::Error(_)));
+
+        let fail = IntoFail::into_fail(Fail::new("already fail"));
+        assert_eq!(fail.to_string(), "already fail");
+    }
+
+    #[test]
+    fn take_cause_of_nested_chain() {
+        let (msg, cause) = three_layers().take_cause();
+        assert_eq!(msg.as_deref(), Some("failed load"));
+        let cause = cause.unwrap();
+        assert_eq!(cause.to_string(), "failed open: no such file");
+
+        let (msg, cause) = cause.take_cause();
+        assert_eq!(msg.as_deref(), Some("failed open"));
+        let cause = cause.unwrap();
+        assert_eq!(cause.to_string(), "no such file");
+        assert_eq!(cause.io_kind(), Some(io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn take_cause_keeps_foreign_error() {
+        let (msg, cause) = Fail::from(io_error()).context("x").take_cause();
+        assert_eq!(msg.as_deref(), Some("x"));
+        let err: io::Error = cause.unwrap().downcast().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn take_cause_of_leaf() {
+        let (msg, cause) = Fail::new("leaf").take_cause();
+        assert_eq!(msg.as_deref(), Some("leaf"));
+        assert!(cause.is_none());
+    }
+
+    #[test]
+    fn default_is_empty() {
+        assert!(Fail::default().is_empty());
+        assert!(!Fail::default().context("populated").is_empty());
+        assert!(!Fail::from(io_error()).is_empty());
+    }
+
+    #[test]
+    fn inspect_fail_runs_once_on_error() {
+        let mut seen = Vec::new();
+        let res = Err::<(), _>(io_error())
+            .inspect_fail(|fail| seen.push(fail.to_string()))
+            .context("failed open");
+
+        assert_eq!(seen, vec!["no such file"]);
+        assert_eq!(res.unwrap_err().to_string(), "failed open: no such file");
+    }
+
+    #[test]
+    fn inspect_fail_is_skipped_on_success() {
+        let mut count = 0;
+        let res = Ok::<_, Fail>(1).inspect_fail(|_| count += 1);
+        assert_eq!(res.unwrap(), 1);
+
+        let res = Some(2).inspect_fail(|_| count += 1);
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn prefix_all_prefixes_every_layer() {
+        let fail = Err::<(), _>(io_error())
+            .context("failed open")
+            .unwrap_err()
+            .prefix_all("sync");
+
+        assert_eq!(fail.to_string(), "sync: failed open: sync: no such file");
+        assert_eq!(fail.layers().count(), 2);
+        assert!(matches!(fail.cause(), CauseRef::Fail(_)));
+    }
+
+    #[test]
+    fn with_cause_builds_layer() {
+        let fail = Fail::with_cause("failed open", io_error());
+        assert_eq!(fail.to_string(), "failed open: no such file");
+        assert!(matches!(fail.cause(), CauseRef::Error(_)));
+
+        let fail = Fail::with_cause("failed load", Error::from_fail(Fail::new("low")));
+        assert_eq!(fail.to_string(), "failed load: low");
+        assert!(matches!(fail.cause(), CauseRef::Fail(_)));
+    }
+
+    #[test]
+    fn poll_context_pending() {
+        let poll: Poll<Result<(), io::Error>> = Poll::Pending;
+        assert!(poll.context("failed read").is_pending());
+    }
+
+    #[test]
+    fn poll_context_ready_ok() {
+        let poll: Poll<Result<i32, io::Error>> = Poll::Ready(Ok(1));
+        match poll.context("failed read") {
+            Poll::Ready(Ok(v)) => assert_eq!(v, 1),
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn poll_context_ready_err() {
+        let poll: Poll<Result<(), io::Error>> = Poll::Ready(Err(io_error()));
+        match poll.context("failed read") {
+            Poll::Ready(Err(fail)) => assert_eq!(fail.to_string(), "failed read: no such file"),
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn display_pads_right_aligned() {
+        let s = format!("{:>30}", Fail::new("failed open").caused_by(io_error()));
+        assert_eq!(s.len(), 30);
+        assert_eq!(s, "     failed open: no such file");
+    }
+
+    #[test]
+    fn display_pads_left_aligned() {
+        let s = format!("{:<30}|", Fail::new("failed open").caused_by(io_error()));
+        assert_eq!(s.len(), 31);
+        assert_eq!(s, "failed open: no such file     |");
+    }
+
+    #[test]
+    fn display_precision_truncates() {
+        let s = format!("{:.11}", Fail::new("failed open").caused_by(io_error()));
+        assert_eq!(s, "failed open");
+    }
+
+    #[test]
+    fn contains_message_finds_deep_layer() {
+        let fail = three_layers();
+        assert!(fail.contains_message("such file"));
+        assert!(fail.contains_message("open"));
+
+        let fail: Fail = vec![Fail::new("a"), Fail::new("b").caused_by(io_error())]
+            .into_iter()
+            .collect();
+        assert!(fail.contains_message("no such"));
+    }
+
+    #[test]
+    fn contains_message_rejects_absent() {
+        let fail = three_layers();
+        assert!(!fail.contains_message("permission"));
+        assert!(!fail.contains_message("open: no"));
+    }
+
+    #[test]
+    fn empty_displays_placeholder() {
+        let fail = Fail::default();
+        assert!(fail.is_empty());
+        assert_eq!(fail.to_string(), "unknown error");
+        assert_eq!(format!("{:#}", fail), "unknown error");
+
+        let fail = Fail::from_parts(None, Some(Fail::new("")));
+        assert!(!fail.is_empty());
+        assert_eq!(fail.to_string(), "unknown error");
+    }
+
+    #[test]
+    fn only_leaf_is_displayed() {
+        let fail = Fail::from_parts(None, Some(Fail::from(io_error())));
+        assert!(!fail.is_empty());
+        assert_eq!(fail.to_string(), "no such file");
+
+        let fail = Fail::new("").caused_by(io_error());
+        assert_eq!(fail.to_string(), "no such file");
+    }
+
+    #[test]
+    fn into_io_error_keeps_kind() {
+        let err = three_layers().into_io_error();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "failed load");
+        let inner = err.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+        assert_eq!(
+            inner.as_fail().to_string(),
+            "failed load: failed open: no such file"
+        );
+
+        let err = Fail::new("plain").into_io_error();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+
+    fn poisoned_mutex() -> std::sync::Mutex<i32> {
+        let mutex = std::sync::Mutex::new(0);
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = mutex.lock().unwrap();
+            panic!("poison");
+        });
+        assert!(mutex.is_poisoned());
+        mutex
+    }
+
+    #[test]
+    fn from_poison_drops_guard() {
+        let mutex = poisoned_mutex();
+        let fail = Fail::from_poison(mutex.lock().unwrap_err());
+        assert_eq!(fail.to_string(), "lock poisoned");
+    }
+
+    #[test]
+    fn poison_context() {
+        let mutex = poisoned_mutex();
+        let fail = mutex.lock().context("failed lock").unwrap_err();
+        assert_eq!(fail.to_string(), "failed lock: lock poisoned");
+
+        let mutex = std::sync::Mutex::new(1);
+        assert_eq!(*mutex.lock().context("failed lock").unwrap(), 1);
+    }
+
+    #[test]
+    fn erase_keeps_chain() {
+        let erased = three_layers().erase();
+        assert_eq!(erased.to_string(), "failed load");
+        assert_eq!(
+            Fail::from_boxed(erased).to_string(),
+            three_layers().to_string()
+        );
+    }
+
+    #[test]
+    fn string_error_context() {
+        let res: Result<(), String> = Err("bad input".to_string());
+        let fail = res.context("failed parse").unwrap_err();
+        assert_eq!(fail.to_string(), "failed parse: bad input");
+        assert!(matches!(fail.cause(), CauseRef::Fail(_)));
+
+        let res: Result<i32, String> = Ok(1);
+        assert_eq!(res.context("failed parse").unwrap(), 1);
+    }
+
+    #[test]
+    fn str_error_context() {
+        let res: Result<(), &str> = Err("bad input");
+        let fail = res.context("failed parse").unwrap_err();
+        assert_eq!(fail.to_string(), "failed parse: bad input");
+
+        let res: Result<(), io::Error> = Err(io_error());
+        assert_eq!(
+            res.context("failed open").unwrap_err().to_string(),
+            "failed open: no such file"
+        );
+    }
+
+    #[test]
+    fn report_lists_causes() {
+        let fail = three_layers().with_location("src/main.rs", 10, 5);
+        let report = fail.report().to_string();
+        assert!(report.contains("Caused by:"));
+        assert_eq!(
+            report,
+            "failed load\n    at src/main.rs:10:5\n\nCaused by:\n    0: failed open\n    1: no such file"
+        );
+
+        let fail = Fail::from(io_error()).context("failed open");
+        let location = fail.location().unwrap();
+        assert_eq!(
+            fail.report().to_string(),
+            format!(
+                "failed open\n    at {}\n\nCaused by:\n    no such file",
+                location
+            )
+        );
+    }
+
+    #[test]
+    fn report_without_cause() {
+        let fail = Fail::new("failed open").with_location("src/main.rs", 1, 1);
+        let report = fail.report().to_string();
+        assert_eq!(report, "failed open\n    at src/main.rs:1:1");
+        assert!(!report.contains("Caused by:"));
+
+        assert_eq!(Fail::default().report().to_string(), "unknown error");
+    }
+
+    #[test]
+    fn report_indents_multiple() {
+        let fail: Fail = vec![Fail::new("a"), Fail::new("b")].into_iter().collect();
+        let fail = fail
+            .context("failed validate")
+            .with_location("src/main.rs", 1, 1);
+        assert_eq!(
+            fail.report().to_string(),
+            "failed validate\n    at src/main.rs:1:1\n\nCaused by:\n    2 errors occurred:\n    1. a\n    2. b"
+        );
+    }
+
+    #[test]
+    fn location_is_captured() {
+        let line = line!() + 1;
+        let fail = Fail::new("failed");
+        let location = fail.location().unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+
+        let res: Result<(), io::Error> = Err(io_error());
+        let line = line!() + 1;
+        let fail = res.context("failed open").unwrap_err();
+        assert_eq!(fail.location().unwrap().line(), line);
+
+        fn convert() -> Result<(), Fail> {
+            Err(io_error())?;
+            Ok(())
+        }
+        let fail = convert().unwrap_err();
+        assert_eq!(fail.location().unwrap().file(), file!());
+        assert!(Fail::default().location().is_none());
+    }
+
+    #[test]
+    fn with_location_overrides() {
+        let fail = Fail::new("failed").with_location("gen.rs", 12, 3);
+        let location = fail.location().unwrap();
+        assert_eq!(location.file(), "gen.rs");
+        assert_eq!(location.line(), 12);
+        assert_eq!(location.column(), 3);
+        assert_eq!(location.to_string(), "gen.rs:12:3");
+
+        let fail = Fail::default().with_location("gen.rs", 1, 1).context("top");
+        assert_eq!(fail.location().unwrap().file(), "gen.rs");
+    }
+
+    #[test]
+    fn context_with_is_lazy_on_success() {
+        let res: Result<i32, io::Error> = Ok(1);
+        let v = res
+            .context_with(|| -> String { panic!("message built on success") })
+            .unwrap();
+        assert_eq!(v, 1);
+
+        let opt = Some(2);
+        let v = opt
+            .context_with(|| -> String { panic!("message built on success") })
+            .unwrap();
+        assert_eq!(v, 2);
+
+        let res: Result<i32, Fail> = Ok(3);
+        let v = res
+            .context_with(|| -> String { panic!("message built on success") })
+            .unwrap();
+        assert_eq!(v, 3);
+    }
+
+    #[test]
+    fn context_with_on_failure() {
+        let res: Result<(), io::Error> = Err(io_error());
+        let fail = res
+            .context_with(|| format!("failed open {}", "a.txt"))
+            .unwrap_err();
+        assert_eq!(fail.to_string(), "failed open a.txt: no such file");
+
+        let fail = None::<()>.context_with(|| "missing").unwrap_err();
+        assert_eq!(fail.to_string(), "missing");
+
+        let fail = Err::<(), Fail>(three_layers())
+            .context_with(|| "top")
+            .unwrap_err();
+        assert_eq!(
+            fail.to_string(),
+            "top: failed load: failed open: no such file"
+        );
+    }
+
+    #[test]
+    fn context_path_includes_path() {
+        let path = std::env::temp_dir().join("tiny_fail_not_exists.txt");
+        let fail = std::fs::File::open(&path)
+            .context_path("open", &path)
+            .unwrap_err();
+
+        let expected = format!("failed to open {}: ", path.display());
+        assert!(fail.to_string().starts_with(&expected));
+        assert!(fail.message_contains(&path.display().to_string()));
+    }
+
+    #[test]
+    fn with_field_is_lazy_on_success() {
+        let res: Result<i32, io::Error> = Ok(1);
+        let v = res
+            .with_field("request", || panic!("field computed on success"))
+            .unwrap();
+        assert_eq!(v, 1);
+
+        let res: Result<i32, Fail> = Ok(2);
+        let v = res
+            .with_field("request", || panic!("field computed on success"))
+            .unwrap();
+        assert_eq!(v, 2);
+    }
+
+    #[test]
+    fn with_field_on_failure() {
+        let res: Result<(), io::Error> = Err(io_error());
+        let fail = res.with_field("path", || "a.txt".to_string()).unwrap_err();
+        assert_eq!(fail.to_string(), "no such file");
+        assert_eq!(fail.fields(), &[("path".to_string(), "a.txt".to_string())]);
+
+        let res: Result<(), Fail> = Err(Fail::new("failed open"));
+        let fail = res
+            .with_field("path", || "a.txt".to_string())
+            .with_field("mode", || "r".to_string())
+            .unwrap_err();
+        assert_eq!(fail.to_string(), "failed open");
+        assert_eq!(fail.fields().len(), 2);
+        assert_eq!(fail.fields()[1], ("mode".to_string(), "r".to_string()));
+    }
+
+    #[derive(Debug)]
+    struct AltError;
+
+    impl fmt::Display for AltError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if f.alternate() {
+                f.write_str("alt error\n  detail")
+            } else {
+                f.write_str("alt error")
+            }
+        }
+    }
+
+    impl error::Error for AltError {}
+
+    #[test]
+    fn alternate_propagates_to_foreign_error() {
+        let fail = Fail::from(AltError).context("failed open");
+        assert_eq!(fail.to_string(), "failed open: alt error");
+        assert_eq!(
+            format!("{:#}", fail),
+            "failed open\ncaused by: alt error\n  detail"
+        );
+
+        let fail = fail.context("failed load");
+        assert_eq!(
+            format!("{:#}", fail),
+            "failed load\ncaused by: failed open\ncaused by: alt error\n  detail"
+        );
+    }
+
+    #[test]
+    fn display_escaped_keeps_single_line() {
+        let fail = Fail::new("line1\nline2\tend").context("failed parse");
+        let escaped = fail.display_escaped().to_string();
+        assert_eq!(escaped, "failed parse: line1\\nline2\\tend");
+        assert!(!escaped.contains('\n'));
+
+        assert_eq!(fail.to_string(), "failed parse: line1\nline2\tend");
+    }
+
+    #[test]
+    fn display_escaped_ansi_sequence() {
+        let fail = Fail::new("\x1b[31mred\x1b[0m").context("bad input");
+        assert_eq!(
+            fail.display_escaped().to_string(),
+            "bad input: \\x1b[31mred\\x1b[0m"
+        );
+
+        let fail = Fail::from(AltError).context("failed");
+        assert_eq!(fail.display_escaped().to_string(), "failed: alt error");
+    }
+
+    #[test]
+    fn join_messages_with_separator() {
+        let fail = three_layers();
+        assert_eq!(
+            fail.join_messages(" / "),
+            "failed load / failed open / no such file"
+        );
+        assert_eq!(
+            fail.join_messages("\n"),
+            "failed load\nfailed open\nno such file"
+        );
+    }
+
+    #[test]
+    fn iter_errors_yields_foreign_error() {
+        let fail = three_layers();
+        let errors: Vec<_> = fail.iter_errors().collect();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is::<io::Error>());
+        assert_eq!(errors[0].to_string(), "no such file");
+
+        assert_eq!(Fail::new("plain").iter_errors().count(), 0);
+    }
+
+    #[test]
+    fn iter_errors_follows_source() {
+        #[derive(Debug)]
+        struct Wrapper(io::Error);
+
+        impl fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("wrapper")
+            }
+        }
+
+        impl error::Error for Wrapper {
+            fn source(&self) -> Option<&(dyn 'static + error::Error)> {
+                Some(&self.0)
+            }
+        }
+
+        let fail: Fail = vec![Fail::from(Wrapper(io_error())), Fail::from(AltError)]
+            .into_iter()
+            .collect();
+
+        let errors: Vec<String> = fail.iter_errors().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec!["wrapper", "no such file", "alt error"]);
+    }
+
+    #[test]
+    fn context_keeps_leaf_downcastable() {
+        let res: Result<(), io::Error> = Err(io_error());
+        let fail = res
+            .context("failed open")
+            .context("failed load")
+            .unwrap_err();
+
+        let err = fail.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "no such file");
+        assert!(fail.downcast_ref::<AltError>().is_none());
+    }
+
+    #[test]
+    fn assert_fail_passes() {
+        let res: Result<(), Fail> = Err(three_layers());
+        assert_fail!(res, "failed open");
+        assert_fail!(Err::<(), Fail>(three_layers()), String::from("such file"),);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't contain \"permission\"")]
+    fn assert_fail_panics_without_substring() {
+        assert_fail!(Err::<(), Fail>(three_layers()), "permission");
+    }
+
+    #[test]
+    fn assert_fail_accepts_error() {
+        let res: Result<(), Error> = Err(three_layers().into_error());
+        assert_fail!(res, "no such file");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "`failed load: failed open: no such file` doesn't contain \"timeout\""
+    )]
+    fn assert_fail_panics_with_chain_for_error() {
+        let res: Result<(), Error> = Err(three_layers().into_error());
+        assert_fail!(res, "timeout");
+    }
+
+    #[test]
+    #[should_panic(expected = "is `Ok`, expected `Err`")]
+    fn assert_fail_panics_on_ok() {
+        assert_fail!(Ok::<(), Fail>(()), "failed");
+    }
+
+    #[test]
+    fn write_chain_matches_display() {
+        let fail = three_layers();
+        let mut buf = String::new();
+        fail.write_chain(&mut buf).unwrap();
+        assert_eq!(buf, fail.to_string());
+
+        let fail: Fail = vec![Fail::new("a"), three_layers()].into_iter().collect();
+        let mut buf = String::new();
+        fail.write_chain(&mut buf).unwrap();
+        assert_eq!(buf, fail.to_string());
+    }
+
+    #[test]
+    fn chain_strings_yields_layers() {
+        let layers: Vec<String> = three_layers().chain_strings().collect();
+        assert_eq!(layers, vec!["failed load", "failed open", "no such file"]);
+        assert_eq!(Fail::default().chain_strings().count(), 0);
+    }
+
+    #[test]
+    fn from_display_uses_display() {
+        struct Status(u16);
+
+        impl fmt::Display for Status {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "unexpected status {}", self.0)
+            }
+        }
+
+        let fail = Fail::from_display(Status(503)).context("failed fetch");
+        assert_eq!(fail.to_string(), "failed fetch: unexpected status 503");
+        assert!(matches!(fail.cause(), CauseRef::Fail(_)));
+    }
+
+    #[test]
+    fn category_is_set_and_inherited() {
+        let fail = Fail::new("no such user").with_category(Category::NotFound);
+        assert_eq!(fail.category(), Category::NotFound);
+
+        let fail = fail.context("failed get user");
+        assert_eq!(fail.category(), Category::NotFound);
+
+        let fail = fail
+            .context("failed handle")
+            .with_category(Category::InvalidInput);
+        assert_eq!(fail.category(), Category::InvalidInput);
+
+        let fail = FailBuilder::new()
+            .message("slow")
+            .category(Category::Timeout)
+            .build();
+        assert_eq!(fail.category(), Category::Timeout);
+    }
+
+    #[test]
+    fn category_is_inferred_from_io_error() {
+        assert_eq!(three_layers().category(), Category::NotFound);
+
+        let fail = Fail::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(fail.category(), Category::PermissionDenied);
+
+        let fail = Fail::from(io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!(fail.category(), Category::Timeout);
+
+        let fail = Fail::from(io::Error::from(io::ErrorKind::BrokenPipe));
+        assert_eq!(fail.category(), Category::Other);
+
+        assert_eq!(Fail::new("plain").category(), Category::Other);
+        assert_eq!(
+            three_layers().with_category(Category::Other).category(),
+            Category::Other
+        );
+    }
+
+    #[test]
+    fn fail_is_one_pointer() {
+        use std::mem::size_of;
+
+        assert_eq!(size_of::<Fail>(), size_of::<usize>());
+        assert_eq!(size_of::<Option<Fail>>(), size_of::<usize>());
+        assert_eq!(size_of::<Result<(), Fail>>(), size_of::<usize>());
+        assert_eq!(size_of::<Error>(), size_of::<usize>());
+    }
+
+    #[derive(Debug)]
+    struct Layered(&'static str, Option<Box<Layered>>);
+
+    impl fmt::Display for Layered {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl error::Error for Layered {
+        fn source(&self) -> Option<&(dyn 'static + error::Error)> {
+            self.1
+                .as_deref()
+                .map(|e| e as &(dyn 'static + error::Error))
+        }
+    }
+
+    #[test]
+    fn context_flatten_records_sources() {
+        let err = Layered(
+            "request failed",
+            Some(Box::new(Layered(
+                "connection reset",
+                Some(Box::new(Layered("broken pipe", None))),
+            ))),
+        );
+
+        let res: Result<(), Layered> = Err(err);
+        let fail = res.context_flatten("failed fetch").unwrap_err();
+        assert_eq!(
+            fail.to_string(),
+            "failed fetch: request failed: connection reset: broken pipe"
+        );
+        assert_eq!(fail.chain_strings().count(), 4);
+        assert_eq!(fail.iter_errors().count(), 0);
+
+        let res: Result<(), Layered> = Err(Layered("request failed", None));
+        assert_eq!(
+            res.context("failed fetch").unwrap_err().to_string(),
+            "failed fetch: request failed"
+        );
+    }
+
+    #[test]
+    fn map_messages_uppercases() {
+        let fail = three_layers()
+            .with_str_code("E_LOAD")
+            .map_messages(|msg| msg.to_uppercase());
+        assert_eq!(fail.to_string(), "FAILED LOAD: FAILED OPEN: no such file");
+        assert_eq!(fail.str_code(), Some("E_LOAD"));
+        assert_eq!(fail.count_causes_of::<io::Error>(), 1);
+        match fail.cause() {
+            CauseRef::Fail(inner) => assert!(matches!(inner.cause(), CauseRef::Error(_))),
+            other => panic!("unexpected cause: {:?}", other),
+        }
+
+        let fail: Fail = vec![Fail::new("a"), Fail::new("b").context("c")]
+            .into_iter()
+            .collect();
+        let fail = fail.map_messages(|msg| msg.to_uppercase());
+        assert_eq!(fail.to_string(), "2 errors occurred:\n1. A\n2. C: B");
+    }
+
+    #[test]
+    fn into_message_chain_drops_foreign_errors() {
+        let fail = three_layers();
+        let rendered = fail.to_string();
+        let fail = fail.into_message_chain();
+        assert_eq!(fail.to_string(), rendered);
+        assert!(fail.downcast_ref::<io::Error>().is_none());
+        assert_eq!(fail.iter_errors().count(), 0);
+
+        let fail = Fail::from(io_error())
+            .with_str_code("E_IO")
+            .into_message_chain();
+        assert_eq!(fail.to_string(), "no such file");
+        assert_eq!(fail.str_code(), Some("E_IO"));
+        assert!(matches!(fail.cause(), CauseRef::None));
+
+        let fail: Fail = vec![Fail::from(io_error()), Fail::new("a")]
+            .into_iter()
+            .collect();
+        let fail = fail.into_message_chain();
+        assert_eq!(
+            fail.to_string(),
+            "2 errors occurred:\n1. no such file\n2. a"
+        );
+        assert_eq!(fail.count_causes_of::<io::Error>(), 0);
+    }
+
+    #[test]
+    fn frozen_matches_live_render() {
+        let live = three_layers();
+        let frozen = three_layers().freeze();
+        assert_eq!(frozen.to_string(), live.to_string());
+        assert_eq!(frozen.as_str(), live.to_string());
+        assert_eq!(format!("{:#}", frozen), format!("{:#}", live));
+        assert_eq!(format!("{:>45}", frozen), format!("{:>45}", live));
+
+        let fail = frozen.into_fail().context("top");
+        assert_eq!(
+            fail.to_string(),
+            "top: failed load: failed open: no such file"
+        );
+    }
+
+    #[test]
+    fn io_kind_walks_chain() {
+        assert_eq!(three_layers().io_kind(), Some(io::ErrorKind::NotFound));
+        assert_eq!(Fail::new("plain").io_kind(), None);
+
+        let fail: Fail = vec![
+            Fail::new("a"),
+            Fail::from(io::Error::from(io::ErrorKind::PermissionDenied)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::PermissionDenied));
+    }
+
+    #[test]
+    fn io_kind_is_cleared_with_io_error() {
+        let fail = Fail::from(io_error()).context("failed open");
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+
+        let fail = fail.into_message_chain();
+        assert_eq!(fail.to_string(), "failed open: no such file");
+        assert_eq!(fail.io_kind(), None);
+
+        let fail = Fail::from(io_error()).replace_cause(Fail::new("internal error"));
+        assert_eq!(fail.to_string(), "internal error");
+        assert_eq!(fail.io_kind(), None);
+
+        let fail = Fail::from(io_error()).caused_by(fmt::Error);
+        assert_eq!(fail.io_kind(), None);
+        let fail = Fail::new("failed open").caused_by(io_error());
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn io_kind_agrees_with_downcast_ref() {
+        let fail = Fail::with_cause("a", io_error())
+            .combine(Fail::from(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+        assert_eq!(
+            fail.downcast_ref::<io::Error>().map(io::Error::kind),
+            fail.io_kind()
+        );
+    }
+
+    #[test]
+    fn io_kind_is_recorded_on_conversion() {
+        fn open() -> Result<(), Fail> {
+            Err(io::Error::from(io::ErrorKind::TimedOut))?;
+            Ok(())
+        }
+
+        let fail = open().unwrap_err();
+        assert_eq!(
+            fail.inner.meta.as_ref().and_then(|meta| meta.io_kind),
+            Some(io::ErrorKind::TimedOut)
+        );
+        assert_eq!(
+            fail.context("failed open").io_kind(),
+            Some(io::ErrorKind::TimedOut)
+        );
+
+        let fail: Fail = io::Error::from(io::ErrorKind::NotFound).into();
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+        let fail = Fail::from_boxed(fail.erase());
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+        assert!(Fail::from(fmt::Error).inner.meta.is_none());
+    }
+
+    #[derive(Debug)]
+    struct Borrowing<'a> {
+        rest: &'a str,
+    }
+
+    impl<'a> fmt::Display for Borrowing<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "unexpected {:?}", self.rest)
+        }
+    }
+
+    impl<'a> error::Error for Borrowing<'a> {}
+
+    fn parse_borrowing(input: &str) -> Result<u32, Borrowing<'_>> {
+        input.parse().map_err(|_| Borrowing { rest: input })
+    }
+
+    #[test]
+    fn context_owned_renders_borrowing_error() {
+        let input = String::from("12x");
+        let fail = parse_borrowing(&input)
+            .context_owned("failed parse")
+            .unwrap_err();
+        drop(input);
+        assert_eq!(fail.to_string(), "failed parse: unexpected \"12x\"");
+        assert_eq!(fail.iter_errors().count(), 0);
+
+        assert_eq!(
+            parse_borrowing("12").context_owned("failed parse").unwrap(),
+            12
+        );
+    }
+
+    #[test]
+    fn try_new_surfaces_fmt_error() {
+        struct Broken;
+
+        impl fmt::Display for Broken {
+            fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        assert!(Fail::try_new(Broken).is_err());
+
+        let fail = Fail::try_new("failed open").unwrap();
+        assert_eq!(fail.to_string(), "failed open");
+        assert!(fail.location().is_some());
+    }
+
+    #[test]
+    fn replace_cause_swaps_leaf() {
+        let fail = three_layers().replace_cause(Fail::new("internal error"));
+        assert_eq!(fail.to_string(), "failed load: failed open: internal error");
+        assert_eq!(fail.count_causes_of::<io::Error>(), 0);
+
+        let fail: Fail = vec![Fail::from(io_error()), Fail::new("b")]
+            .into_iter()
+            .collect();
+        let fail = fail
+            .context("failed validate")
+            .replace_cause(Fail::new("invalid input"));
+        assert_eq!(fail.to_string(), "failed validate: invalid input");
+    }
+
+    #[test]
+    fn replace_cause_attaches_without_cause() {
+        let fail = Fail::new("low")
+            .context("high")
+            .replace_cause(Fail::new("generic"));
+        assert_eq!(fail.to_string(), "high: low: generic");
+    }
+
+    #[test]
+    fn with_field_attaches_fields() {
+        let fail = Fail::new("failed request")
+            .with_field("method", "GET")
+            .with_field("status", 503);
+        assert_eq!(
+            fail.fields(),
+            &[
+                ("method".to_string(), "GET".to_string()),
+                ("status".to_string(), "503".to_string()),
+            ]
+        );
+        assert_eq!(fail.to_string(), "failed request");
+        assert!(fail.context("top").fields().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn context_path_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::env::temp_dir().join(OsStr::from_bytes(b"tiny_fail_\xff.txt"));
+        assert!(path.to_str().is_none());
+
+        let fail = std::fs::File::open(&path)
+            .context_path("open", &path)
+            .unwrap_err();
+        let expected = format!("failed to open {}: ", path.display());
+        assert!(fail.to_string().starts_with(&expected));
+        assert!(fail.to_string().contains("tiny_fail_\u{fffd}.txt"));
+    }
+
+    #[derive(Debug)]
+    enum AppError {
+        NotFound(&'static str),
+        Invalid,
+    }
+
+    impl From<AppError> for Fail {
+        fn from(err: AppError) -> Fail {
+            match err {
+                AppError::NotFound(name) => Fail::new(format!("{} is not found", name)),
+                AppError::Invalid => Fail::new_static("invalid"),
+            }
+        }
+    }
+
+    #[test]
+    fn context_with_custom_conversion() {
+        let res: Result<(), AppError> = Err(AppError::NotFound("user"));
+        let fail = res.context("failed get user").unwrap_err();
+        assert_eq!(fail.to_string(), "failed get user: user is not found");
+
+        let res: Result<(), AppError> = Err(AppError::Invalid);
+        let fail = res.context_with(|| "failed parse").unwrap_err();
+        assert_eq!(fail.to_string(), "failed parse: invalid");
+    }
+
+    #[test]
+    fn context_keeps_single_layer_for_errors() {
+        let res: Result<(), io::Error> = Err(io_error());
+        let fail = res.context("failed open").unwrap_err();
+        assert!(matches!(fail.cause(), CauseRef::Error(_)));
+        assert_eq!(fail.location().unwrap().file(), file!());
+    }
+
+    #[test]
+    fn context_flatten_through_fail() {
+        let res: Result<(), Fail> = Err(three_layers());
+        let fail = res.context_flatten("top").unwrap_err();
+        assert_eq!(
+            fail.to_string(),
+            "top: failed load: failed open: no such file"
+        );
+        assert_eq!(fail.iter_errors().count(), 0);
+    }
+
+    #[test]
+    fn format_deep_chain() {
+        let mut fail = Fail::new("root");
+        for _ in 0..100_000 {
+            fail = Err::<(), _>(fail).context("layer").unwrap_err();
+        }
+
+        let rendered = fail.to_string();
+        assert!(rendered.starts_with("layer: layer: "));
+        assert!(rendered.ends_with(": root"));
+
+        let debug = format!("{:?}", fail);
+        assert_eq!(debug.matches("Layer {").count(), 100_001);
+    }
+
+    #[test]
+    fn debug_lists_layers() {
+        let fail = Fail::new("low").context("high");
+        let debug = format!("{:?}", fail);
+        assert!(debug.starts_with("Fail { layers: [Layer { msg: Some(\"high\")"));
+        assert!(debug.contains("Layer { msg: Some(\"low\")"));
+
+        let debug = format!("{:?}", three_layers());
+        assert!(debug.contains("error: Custom { kind: NotFound"));
+    }
+
+    #[test]
+    fn drop_deep_chain() {
+        let mut fail = Fail::new("root");
+        for _ in 0..100_000 {
+            fail = Err::<(), _>(fail).context("layer").unwrap_err();
+        }
+
+        drop(fail);
+    }
+
+    #[test]
+    fn take_message_keeps_cause() {
+        let mut fail = Err::<(), _>(Fail::new("root")).context("top").unwrap_err();
+
+        assert_eq!(fail.take_message().as_deref(), Some("top"));
+        assert_eq!(fail.to_string(), "root");
+        assert_eq!(fail.take_message(), None);
+    }
+
+    #[test]
+    fn truncate_messages_at_char_boundary() {
+        // Each "あ" is 3 bytes.
+        let fail = Fail::new("あいう").context("short");
+
+        assert_eq!(fail.truncate_messages(6).to_string(), "short: あい…");
+        assert_eq!(fail.truncate_messages(7).to_string(), "short: あい…");
+        assert_eq!(fail.truncate_messages(9).to_string(), "short: あいう");
+        assert_eq!(fail.truncate_messages(0).to_string(), "…: …");
+    }
+
+    #[test]
+    fn suggestion_is_shown_in_alternate() {
+        let fail = Fail::new("unknown subcommand 'biuld'")
+            .with_suggestion("build")
+            .context("failed parse args");
+
+        assert_eq!(fail.suggestion(), Some("build"));
+        assert_eq!(
+            fail.to_string(),
+            "failed parse args: unknown subcommand 'biuld'"
+        );
+        assert_eq!(
+            format!("{:#}", fail),
+            "failed parse args\ncaused by: unknown subcommand 'biuld'\nhelp: did you mean 'build'?"
+        );
+    }
+
+    #[test]
+    fn suggestion_is_absent_by_default() {
+        let fail = Fail::new("low").context("high");
+
+        assert_eq!(fail.suggestion(), None);
+        assert_eq!(format!("{:#}", fail), "high\ncaused by: low");
+    }
+
+    #[test]
+    fn combine_keeps_order() {
+        let fail = Fail::new("primary failed").combine(Fail::new("fallback failed"));
+        assert_eq!(
+            fail.to_string(),
+            "2 errors occurred:\n1. primary failed\n2. fallback failed"
+        );
+
+        let fail = fail.combine(Fail::new("cleanup failed"));
+        assert_eq!(
+            fail.to_string(),
+            "3 errors occurred:\n1. primary failed\n2. fallback failed\n3. cleanup failed"
+        );
+    }
+
+    #[cfg(feature = "timestamp")]
+    #[test]
+    fn timestamp_is_captured() {
+        use std::time::Duration;
+
+        let before = SystemTime::now();
+        let fail = Fail::new("low");
+        std::thread::sleep(Duration::from_millis(10));
+        let fail = fail.context("high");
+        let after = SystemTime::now();
+
+        let timestamp = fail.timestamp().unwrap();
+        assert!(before <= timestamp && timestamp <= after);
+        match fail.cause() {
+            CauseRef::Fail(cause) => assert!(cause.timestamp().unwrap() < timestamp),
+            cause => panic!("unexpected cause: {:?}", cause),
+        }
+    }
+
+    #[cfg(not(feature = "timestamp"))]
+    #[test]
+    fn timestamp_is_none_without_feature() {
+        assert_eq!(Fail::new("x").timestamp(), None);
+    }
+
+    #[test]
+    fn ok_or_log_discards_failure() {
+        assert_eq!("1".parse::<i32>().ok_or_log("failed parse"), Some(1));
+        assert_eq!("x".parse::<i32>().ok_or_log("failed parse"), None);
+        assert_eq!(None::<i32>.ok_or_log("no value"), None);
+    }
+
+    #[test]
+    fn combine_keeps_chains_as_siblings() {
+        let primary = Fail::new("connection reset")
+            .context("failed send")
+            .context("failed save");
+        let cleanup = Fail::new("permission denied").context("failed remove temp file");
+        let fail = primary.combine(cleanup);
+
+        assert_eq!(
+            fail.to_string(),
+            "2 errors occurred:\n\
+             1. failed save: failed send: connection reset\n\
+             2. failed remove temp file: permission denied"
+        );
+        assert_eq!(
+            format!("{:#}", fail),
+            "2 errors occurred:\n\
+             1. failed save\n   caused by: failed send\n   caused by: connection reset\n\
+             2. failed remove temp file\n   caused by: permission denied"
+        );
+    }
+
+    #[test]
+    fn separator_is_configurable() {
+        let fail = Fail::new("no such file")
+            .context("failed open")
+            .context("failed load")
+            .with_separator(" -> ");
+
+        assert_eq!(
+            fail.to_string(),
+            "failed load -> failed open -> no such file"
+        );
+        assert_eq!(
+            format!("{:#}", fail),
+            "failed load\ncaused by: failed open\ncaused by: no such file"
+        );
+        assert_eq!(
+            fail.context("top").to_string(),
+            "top -> failed load -> failed open -> no such file"
+        );
+    }
+
+    #[test]
+    fn custom_separator_reaches_single_line_helpers() {
+        let fail = Fail::new("no such file")
+            .context("failed open")
+            .with_separator(" -> ");
+
+        let mut written = String::new();
+        fail.write_chain(&mut written).unwrap();
+        assert_eq!(written, "failed open -> no such file");
+        assert_eq!(
+            fail.display_escaped().to_string(),
+            "failed open -> no such file"
+        );
+        assert_eq!(fail.freeze().to_string(), "failed open -> no such file");
+    }
+
+    #[test]
+    fn context_timed_shows_elapsed() {
+        use std::time::Duration;
+
+        let start = Instant::now();
+        std::thread::sleep(Duration::from_millis(20));
+        let fail = Err::<(), _>(Fail::new("timeout"))
+            .context_timed("db query", start)
+            .unwrap_err();
+
+        // The exact duration depends on the machine, so only its presence is checked.
+        let msg = fail.chain_strings().next().unwrap();
+        let elapsed = msg.strip_prefix("db query failed after ").unwrap();
+        assert!(elapsed.ends_with('s') && elapsed.contains('.'), "{}", msg);
+        assert!(fail.to_string().ends_with(": timeout"));
+    }
+
+    #[test]
+    fn fingerprint_ignores_numbers() {
+        let a = Fail::new("object at 0x7ffd3a10 is freed").context("failed job 12");
+        let b = Fail::new("object at 0x55e0 is freed").context("failed job 3456");
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        let c = Fail::new("object at 0x55e0 is locked").context("failed job 3456");
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        assert_ne!(
+            Fail::new("a").context("b").fingerprint(),
+            Fail::new("ab").fingerprint()
+        );
+    }
+
+    #[test]
+    fn fingerprint_with_normalizer() {
+        let user = |name: &str| {
+            Err::<(), _>(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no user {}", name),
+            ))
+            .context(format!("failed load {}", name))
+            .unwrap_err()
+        };
+        let normalize = |msg: &str| msg.replace("alice", "<user>").replace("bob", "<user>");
+
+        assert_ne!(user("alice").fingerprint(), user("bob").fingerprint());
+        assert_eq!(
+            user("alice").fingerprint_with(normalize),
+            user("bob").fingerprint_with(normalize)
+        );
+    }
+
+    #[test]
+    fn normalize_numbers_keeps_text() {
+        assert_eq!(normalize_numbers("line 42, col 7"), "line #, col #");
+        assert_eq!(normalize_numbers("at 0xdeadBEEF!"), "at #!");
+        assert_eq!(normalize_numbers("0x"), "#");
+        assert_eq!(normalize_numbers("日本 10"), "日本 #");
+    }
+
+    #[test]
+    fn std_errors_are_converted() {
+        use std::io::Read;
+
+        fn convert(input: &[u8]) -> Result<f64, Fail> {
+            let s = std::str::from_utf8(input)?;
+            let s = String::from_utf8(s.as_bytes().to_vec())?;
+            let n: i32 = s.split('.').next().unwrap().parse()?;
+            let x: f64 = s.parse()?;
+            let mut out = String::new();
+            fmt::write(&mut out, format_args!("{}", n))?;
+            io::Cursor::new(out.as_bytes()).read_exact(&mut [0; 8])?;
+            Ok(x)
+        }
+
+        assert!(convert(b"\xff")
+            .unwrap_err()
+            .downcast_ref::<std::str::Utf8Error>()
+            .is_some());
+        assert!(convert(b"x")
+            .unwrap_err()
+            .downcast_ref::<std::num::ParseIntError>()
+            .is_some());
+        assert!(convert(b"1.x")
+            .unwrap_err()
+            .downcast_ref::<std::num::ParseFloatError>()
+            .is_some());
+        assert_eq!(
+            convert(b"1.5").unwrap_err().io_kind(),
+            Some(io::ErrorKind::UnexpectedEof)
+        );
+
+        fn from_utf8(bytes: Vec<u8>) -> Result<String, Fail> {
+            Ok(String::from_utf8(bytes)?)
+        }
+        assert!(from_utf8(vec![0xff])
+            .unwrap_err()
+            .downcast_ref::<std::string::FromUtf8Error>()
+            .is_some());
+
+        fn write() -> Result<(), Fail> {
+            Err(fmt::Error)?;
+            Ok(())
+        }
+        assert!(write().unwrap_err().downcast_ref::<fmt::Error>().is_some());
+
+        fn rethrow() -> Result<(), Fail> {
+            Err(Error::from_fail(Fail::new("inner")))?;
+            Ok(())
+        }
+        assert_eq!(rethrow().unwrap_err().to_string(), "inner");
+    }
+
+    #[test]
+    fn downcast_takes_leaf_error() {
+        let err: io::Error = three_layers().downcast().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "no such file");
+    }
+
+    #[test]
+    fn downcast_returns_fail_on_mismatch() {
+        let fail = three_layers().downcast::<fmt::Error>().unwrap_err();
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+        assert!(fail.downcast_ref::<io::Error>().is_some());
+
+        let fail = Fail::new("low").context("high");
+        assert_eq!(
+            fail.downcast::<io::Error>().unwrap_err().to_string(),
+            "high: low"
+        );
+    }
+
+    #[test]
+    fn display_with_location_shows_capture_site() {
+        let fail = Fail::new("low").context("high");
+        let rendered = fail.display_with_location().to_string();
+        assert!(rendered.contains(file!()), "{}", rendered);
+
+        let fail = Fail::new("no such file")
+            .with_location("src/fs.rs", 3, 1)
+            .context("failed open")
+            .with_location("src/main.rs", 10, 5);
+        assert_eq!(
+            fail.display_with_location().to_string(),
+            "[src/main.rs:10] failed open: [src/fs.rs:3] no such file"
+        );
+        assert_eq!(
+            format!("{:#}", fail.display_with_location()),
+            "[src/main.rs:10] failed open\ncaused by: [src/fs.rs:3] no such file"
+        );
+
+        let fail = Fail::from_parts(Some("plain".to_string()), None);
+        assert_eq!(fail.display_with_location().to_string(), "plain");
+    }
+
+    #[test]
+    fn display_markdown_nests_causes() {
+        let fail = Fail::new("no such file")
+            .context("failed open")
+            .with_note("check the path");
+        assert_eq!(
+            fail.display_markdown().to_string(),
+            "- failed open\n  - no such file\n\n**Notes:**\n\n- check the path"
+        );
+
+        let fail: Fail = vec![Fail::new("a"), Fail::new("b")].into_iter().collect();
+        assert_eq!(
+            fail.context("top").display_markdown().to_string(),
+            "- top\n  - 2 errors occurred:\n    1. a\n    2. b"
+        );
+        assert_eq!(
+            Fail::default().display_markdown().to_string(),
+            "- unknown error"
+        );
+    }
+
+    #[test]
+    fn retry_until_success() {
+        let mut count = 0;
+        let res = retry(5, || {
+            count += 1;
+            if count < 3 {
+                Err(Fail::new("busy").transient())
+            } else {
+                Ok(count)
+            }
+        });
+
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn retry_gives_up() {
+        let mut count = 0;
+        let fail = retry(5, || -> Result<(), Fail> {
+            count += 1;
+            Err(Fail::new("invalid input"))
+        })
+        .unwrap_err();
+        assert_eq!(count, 1);
+        assert_eq!(fail.to_string(), "gave up after 1 attempt: invalid input");
+
+        let mut count = 0;
+        let fail = retry(3, || -> Result<(), Fail> {
+            count += 1;
+            Err(Fail::new("busy").transient())
+        })
+        .unwrap_err();
+        assert_eq!(count, 3);
+        assert_eq!(fail.to_string(), "gave up after 3 attempts: busy");
+        assert!(fail.is_transient());
+    }
+
+    #[test]
+    fn visit_counts_layers() {
+        let mut count = 0;
+        three_layers().visit(|_| count += 1);
+        assert_eq!(count, 2);
+
+        let fail: Fail = vec![three_layers(), Fail::new("a").context("b")]
+            .into_iter()
+            .collect();
+        let mut msgs = Vec::new();
+        fail.visit(|fail| msgs.push(fail.inner.msg.as_deref().map(str::to_owned)));
+        assert_eq!(
+            msgs,
+            vec![
+                None,
+                Some("failed load".to_owned()),
+                Some("failed open".to_owned()),
+                Some("b".to_owned()),
+                Some("a".to_owned()),
+            ]
+        );
+
+        let mut errors = Vec::new();
+        fail.visit_errors(|e| errors.push(e.to_string()));
+        assert_eq!(errors, vec!["no such file"]);
+    }
+
+    #[test]
+    fn structural_eq_ignores_numbers_and_paths() {
+        let fail = |line: u32, dir: &str| {
+            Err::<(), _>(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid token at line {}", line),
+            ))
+            .context(format!("failed read '{}/config.toml'", dir))
+            .unwrap_err()
+        };
+
+        assert!(fail(3, "/tmp/.tmpA1b2").structural_eq(&fail(17, "/tmp/.tmpZz9")));
+        assert!(fail(3, "C:\\Temp\\x").structural_eq(&fail(3, "/tmp/y")));
+        assert!(!fail(3, "/tmp/a").structural_eq(&fail(3, "relative")));
+        assert!(!fail(3, "/tmp/a").structural_eq(&fail(3, "/tmp/a").context("top")));
+        assert!(!Fail::new("a 1").structural_eq(&Fail::new("b 1")));
+    }
+
+    #[test]
+    fn normalize_paths_keeps_text() {
+        assert_eq!(normalize_paths("open /tmp/a.txt: x"), "open <path> x");
+        assert_eq!(normalize_paths("open (/tmp/a), ok"), "open (<path>), ok");
+        assert_eq!(normalize_paths("path=D:\\data\\a"), "path=<path>");
+        assert_eq!(normalize_paths("a/b and 1/2"), "a/b and 1/2");
+        assert_eq!(normalize_paths("日本 /x"), "日本 <path>");
+    }
+
+    #[test]
+    fn from_boxed_unwraps_error() {
+        let err: Box<dyn error::Error + Send + Sync> = Box::new(io_error());
+        let fail = Fail::from_boxed(err);
+        assert_eq!(fail.to_string(), "no such file");
+        assert_eq!(fail.io_kind(), Some(io::ErrorKind::NotFound));
+        assert!(fail.downcast_ref::<io::Error>().is_some());
+
+        let err: Box<dyn error::Error + Send + Sync> = Box::new(Error::from_fail(three_layers()));
+        let fail = Fail::from_boxed(err);
+        assert_eq!(fail.inner.msg.as_deref(), Some("failed load"));
+    }
+
+    #[test]
+    fn from_parts_builds_chain() {
+        let leaf = Fail::from_parts(Some("leaf".to_string()), None);
+        let middle = Fail::from_parts(Some("middle".to_string()), Some(leaf));
+        let top = Fail::from_parts(Some("top".to_string()), Some(middle));
+
+        let expected = Err::<(), _>(Fail::new("leaf"))
+            .context("middle")
+            .context("top")
+            .unwrap_err();
+        assert_eq!(top.to_string(), expected.to_string());
+        assert_eq!(top.to_string(), "top: middle: leaf");
+    }
+
+    #[test]
+    fn from_parts_without_message() {
+        let fail = Fail::from_parts(None, Some(Fail::new("leaf")));
+        assert_eq!(fail.to_string(), "leaf");
+
+        let empty = Fail::from_parts(None, None);
+        assert_eq!(empty.to_string(), "unknown error");
+    }
+
+    fn three_layers() -> Fail {
+        Err::<(), _>(io_error())
+            .context("failed open")
+            .context("failed load")
+            .unwrap_err()
+    }
+
+    #[test]
+    fn display_alternate_is_multi_line() {
+        let fail = three_layers();
+        assert_eq!(
+            format!("{:#}", fail),
+            "failed load\ncaused by: failed open\ncaused by: no such file"
+        );
+    }
+
+    #[test]
+    fn verbose_separator_is_multi_line() {
+        let fail = three_layers();
+        let render = |verbose| {
+            Joined {
+                fail: &fail,
+                sep: fail.display_separator(verbose),
+            }
+            .to_string()
+        };
+
+        assert_eq!(render(false), "failed load: failed open: no such file");
+        assert_eq!(
+            render(true),
+            "failed load\ncaused by: failed open\ncaused by: no such file"
+        );
+        assert_eq!(
+            fail.with_separator(" -> ").display_separator(true),
+            "\ncaused by: "
+        );
+    }
+
+    #[test]
+    fn collect_fails_partitions() {
+        let items = vec![
+            Ok(1),
+            Err(Fail::new("first")),
+            Ok(2),
+            Err(Fail::new("second")),
+            Ok(3),
+        ];
+
+        let (oks, fails) = items.into_iter().collect_fails();
+        assert_eq!(oks, vec![1, 2, 3]);
+        let fails: Vec<String> = fails.iter().map(|f| f.to_string()).collect();
+        assert_eq!(fails, vec!["first", "second"]);
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        use std::task::{RawWaker, RawWakerVTable, Waker};
+
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+        let mut cx = task::Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(out) = future.as_mut().poll(&mut cx) {
+                return out;
+            }
+        }
+    }
+
+    #[test]
+    fn future_context_on_error() {
+        let fut = async { Err::<(), _>(io_error()) };
+        let fail = block_on(fut.context("failed open")).unwrap_err();
+        assert_eq!(fail.to_string(), "failed open: no such file");
+    }
+
+    #[test]
+    fn future_context_on_fail() {
+        let fut = async { Err::<(), _>(Fail::new("low")) };
+        let fail = block_on(fut.context("high")).unwrap_err();
+        assert_eq!(fail.to_string(), "high: low");
+    }
+
+    #[test]
+    fn future_context_on_ok() {
+        let fut = async { Ok::<_, Fail>(42) };
+        assert_eq!(block_on(fut.context("unused")).unwrap(), 42);
+    }
+
+    #[test]
+    fn context_structured_records_source() {
+        let res: Result<(), _> = Err(io_error());
+        let fail = res.context_structured("failed open").unwrap_err();
+
+        assert_eq!(fail.to_string(), "failed open: no such file");
+        assert_eq!(
+            fail.fields(),
+            &[(SOURCE_FIELD.to_string(), "no such file".to_string())]
+        );
+    }
+
+    #[test]
+    fn context_structured_records_source_of_fail() {
+        let res: Result<(), _> = Err(io_error());
+        let fail = res
+            .context("failed open")
+            .context_structured("failed load")
+            .unwrap_err();
+
+        assert_eq!(fail.to_string(), "failed load: failed open: no such file");
+        assert_eq!(
+            fail.fields(),
+            &[(
+                SOURCE_FIELD.to_string(),
+                "failed open: no such file".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn context_structured_on_none_has_no_source() {
+        let fail = None::<()>.context_structured("missing").unwrap_err();
+        assert_eq!(fail.to_string(), "missing");
+        assert!(fail.fields().is_empty());
     }
 }
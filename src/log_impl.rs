@@ -0,0 +1,115 @@
+use log::{log, Level};
+
+use crate::Fail;
+
+impl Fail {
+    /// Emits this failure as a log record at the given level.
+    ///
+    /// At `Debug` and `Trace` levels, the multi-line alternate form is used.
+    pub fn log(&self, level: Level) {
+        match level {
+            Level::Debug | Level::Trace => log!(level, "{:#}", self),
+            _ => log!(level, "{}", self),
+        }
+    }
+
+    /// Emits this failure as a log record at `Error` level.
+    pub fn log_error(&self) {
+        self.log(Level::Error);
+    }
+
+    /// Emits this failure as a log record at `Warn` level.
+    pub fn log_warn(&self) {
+        self.log(Level::Warn);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+
+    use crate::{Fail, FailExt};
+
+    thread_local! {
+        static RECORDS: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // Records are captured per thread, since tests run in parallel.
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            RECORDS.with(|records| {
+                records
+                    .borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: Capture = Capture;
+
+    fn capture<F: FnOnce()>(f: F) -> Vec<(Level, String)> {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+
+        f();
+        RECORDS.with(|records| records.borrow_mut().drain(..).collect())
+    }
+
+    #[test]
+    fn log_emits_chain() {
+        let fail = Fail::new("low").context("high");
+        let records = capture(|| {
+            fail.log_error();
+            fail.log_warn();
+            fail.log(Level::Debug);
+        });
+
+        assert_eq!(
+            records,
+            vec![
+                (Level::Error, "high: low".to_string()),
+                (Level::Warn, "high: low".to_string()),
+                (Level::Debug, "high\ncaused by: low".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ok_or_log_logs_failure() {
+        let mut value = None;
+        let records = capture(|| {
+            value = Err::<i32, _>(Fail::new("low")).ok_or_log("high");
+            assert_eq!(Ok::<_, Fail>(1).ok_or_log("unused"), Some(1));
+        });
+
+        assert_eq!(value, None);
+        assert_eq!(records, vec![(Level::Error, "high: low".to_string())]);
+    }
+
+    #[test]
+    fn log_contains_all_layers() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let fail = Err::<(), _>(io_err)
+            .context("failed open")
+            .context("failed load")
+            .unwrap_err();
+        let records = capture(|| fail.log(Level::Info));
+
+        assert_eq!(records.len(), 1);
+        let (level, msg) = &records[0];
+        assert_eq!(*level, Level::Info);
+        for layer in &["failed load", "failed open", "no such file"] {
+            assert!(msg.contains(layer), "{:?} is not in {:?}", layer, msg);
+        }
+    }
+}
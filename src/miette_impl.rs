@@ -0,0 +1,84 @@
+use std::fmt;
+
+use miette::Diagnostic;
+
+use crate::Error;
+
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.0
+            .str_code()
+            .map(|code| Box::new(code) as Box<dyn fmt::Display>)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let notes: Vec<&str> = self.0.notes().collect();
+        if notes.is_empty() {
+            None
+        } else {
+            Some(Box::new(notes.join("\n")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use miette::Diagnostic;
+
+    use crate::{Error, Fail};
+
+    #[test]
+    fn help_shows_notes() {
+        let err = Error(
+            Fail::new("low")
+                .with_note("check the file")
+                .context("high")
+                .with_note("run with --verbose"),
+        );
+
+        let help = err.help().unwrap().to_string();
+        assert_eq!(help, "run with --verbose\ncheck the file");
+    }
+
+    #[test]
+    fn no_help_without_notes() {
+        let err = Error(Fail::new("x"));
+        assert!(err.help().is_none());
+    }
+
+    #[test]
+    fn code_is_str_code() {
+        let err = Error(
+            Fail::new("not found")
+                .with_str_code("E_NOT_FOUND")
+                .with_note("check the path"),
+        );
+
+        assert_eq!(err.code().unwrap().to_string(), "E_NOT_FOUND");
+        assert_eq!(err.help().unwrap().to_string(), "check the path");
+    }
+
+    #[test]
+    fn report_shows_each_message_once() {
+        let err = Error(
+            Fail::new("no such file")
+                .context("failed open")
+                .context("failed load"),
+        );
+
+        let mut report = String::new();
+        miette::NarratableReportHandler::new()
+            .render_report(&mut report, &err)
+            .unwrap();
+        assert_eq!(
+            report,
+            "failed load\n    Diagnostic severity: error\n    Caused by: failed open\n    Caused by: no such file\n"
+        );
+    }
+
+    #[test]
+    fn no_code_without_str_code() {
+        let err = Error(Fail::new("x"));
+        assert!(err.code().is_none());
+    }
+}
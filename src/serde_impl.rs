@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+use std::error;
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::{Fail, FailCause};
+
+/// Serialized as nested map like `{"message": "failed load", "code": "E_LOAD", "cause": {"message": "no such file"}}`.
+/// Key-value fields are serialized as `"fields"` list of pairs.
+///
+/// Foreign errors are serialized as `{"message": ..}` by their `Display`, and aggregated `Fail`s are serialized as `"causes"` list.
+///
+/// Serialization recurses once per layer, as the format is nested. Very deep chains (tens of thousands of layers) may overflow the stack,
+/// and deserializers may have their own limit, like 128 levels of `serde_json`.
+impl Serialize for Fail {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(msg) = &self.inner.msg {
+            map.serialize_entry("message", msg)?;
+        }
+        if let Some(code) = self
+            .inner
+            .meta
+            .as_deref()
+            .and_then(|meta| meta.str_code.as_deref())
+        {
+            map.serialize_entry("code", code)?;
+        }
+        if !self.fields().is_empty() {
+            map.serialize_entry("fields", self.fields())?;
+        }
+        match &self.inner.cause {
+            None => {}
+            Some(FailCause::Error(e)) => map.serialize_entry("cause", &ErrorLeaf(e.as_ref()))?,
+            Some(FailCause::Fail(fail)) => map.serialize_entry("cause", fail)?,
+            Some(FailCause::Multiple(fails)) => map.serialize_entry("causes", fails)?,
+        }
+        map.end()
+    }
+}
+
+struct ErrorLeaf<'a>(&'a (dyn 'static + error::Error));
+
+impl<'a> Serialize for ErrorLeaf<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("message", &self.0.to_string())?;
+        map.end()
+    }
+}
+
+/// Deserialized from the format of `Serialize`.
+///
+/// Foreign errors can't be restored, so they become message-only `Fail`s.
+impl<'de> Deserialize<'de> for Fail {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Fail, D::Error> {
+        FailRepr::deserialize(deserializer).map(FailRepr::into_fail)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct FailRepr {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    fields: Vec<(String, String)>,
+    #[serde(default)]
+    cause: Option<Box<FailRepr>>,
+    #[serde(default)]
+    causes: Option<Vec<FailRepr>>,
+}
+
+impl FailRepr {
+    fn into_fail(self) -> Fail {
+        let fail = match self.causes {
+            Some(causes) => Fail::from_raw(
+                self.message.map(Cow::Owned),
+                Some(FailCause::Multiple(
+                    causes.into_iter().map(FailRepr::into_fail).collect(),
+                )),
+            ),
+            None => Fail::from_parts(self.message, self.cause.map(|cause| cause.into_fail())),
+        };
+
+        let fail = self
+            .fields
+            .into_iter()
+            .fold(fail, |fail, (key, value)| fail.with_field(key, value));
+        match self.code {
+            Some(code) => fail.with_str_code(code),
+            None => fail,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use serde_json::json;
+
+    use crate::Fail;
+
+    #[test]
+    fn serialize_chain() {
+        let fail = Fail::from(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            .context("failed open")
+            .context("failed load");
+
+        assert_eq!(
+            serde_json::to_value(&fail).unwrap(),
+            json!({
+                "message": "failed load",
+                "cause": {
+                    "message": "failed open",
+                    "cause": { "message": "no such file" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_code() {
+        let fail = Fail::new("no such user")
+            .with_code("E_NOT_FOUND")
+            .context("failed get user");
+
+        assert_eq!(
+            serde_json::to_value(&fail).unwrap(),
+            json!({
+                "message": "failed get user",
+                "cause": { "message": "no such user", "code": "E_NOT_FOUND" }
+            })
+        );
+    }
+
+    #[test]
+    fn round_trip_chain() {
+        let fail = Fail::from(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            .context("failed open")
+            .with_code("E_OPEN")
+            .context("failed load");
+
+        let json = serde_json::to_string(&fail).unwrap();
+        let restored: Fail = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.to_string(), fail.to_string());
+        assert_eq!(restored.code(), Some("E_OPEN"));
+        assert_eq!(restored.count_causes_of::<io::Error>(), 0);
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+    }
+
+    #[test]
+    fn round_trip_multiple() {
+        let fail: Fail = vec![Fail::new("a"), Fail::new("b").context("c")]
+            .into_iter()
+            .collect();
+        let fail = fail.context("failed validate");
+
+        let json = serde_json::to_string(&fail).unwrap();
+        let restored: Fail = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.to_string(), fail.to_string());
+    }
+
+    #[test]
+    fn deserialize_empty() {
+        let fail: Fail = serde_json::from_str("{}").unwrap();
+        assert!(fail.is_empty());
+    }
+
+    #[test]
+    fn round_trip_fields() {
+        let fail = Fail::new("failed request")
+            .with_field("method", "GET")
+            .with_field("status", 503);
+
+        let value = serde_json::to_value(&fail).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "message": "failed request",
+                "fields": [["method", "GET"], ["status", "503"]]
+            })
+        );
+
+        let restored: Fail = serde_json::from_value(value).unwrap();
+        assert_eq!(restored.fields(), fail.fields());
+    }
+
+    #[test]
+    fn serialize_multiple() {
+        let fail: Fail = vec![Fail::new("a"), Fail::new("b")].into_iter().collect();
+
+        assert_eq!(
+            serde_json::to_value(&fail).unwrap(),
+            json!({ "causes": [{ "message": "a" }, { "message": "b" }] })
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use tracing_error::{SpanTrace, SpanTraceStatus};
+
+use crate::Fail;
+
+impl Fail {
+    /// Returns the span trace captured where this failure occurred.
+    ///
+    /// Span traces are captured only under a subscriber with `tracing_error::ErrorLayer`.
+    pub fn span_trace(&self) -> Option<&SpanTrace> {
+        self.layers()
+            .find_map(|fail| fail.inner.span_trace.as_ref())
+    }
+}
+
+pub(crate) fn capture() -> Option<SpanTrace> {
+    let span_trace = SpanTrace::capture();
+    if span_trace.status() == SpanTraceStatus::CAPTURED {
+        Some(span_trace)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tracing_error::ErrorLayer;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    use crate::{Fail, FailExt};
+
+    #[test]
+    fn span_trace_is_captured() {
+        let subscriber = Registry::default().with(ErrorLayer::default());
+        let fail = tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("load_config").entered();
+            Err::<(), _>(Fail::new("low")).context("high").unwrap_err()
+        });
+
+        let span_trace = fail.span_trace().unwrap().to_string();
+        assert!(span_trace.contains("load_config"), "{}", span_trace);
+        assert!(format!("{:#}", fail).contains("span trace:"));
+        assert_eq!(fail.to_string(), "high: low");
+    }
+
+    #[test]
+    fn span_trace_is_not_captured_without_layer() {
+        assert!(Fail::new("x").span_trace().is_none());
+    }
+}
@@ -0,0 +1,122 @@
+use tracing::{event, Level};
+
+use crate::Fail;
+
+macro_rules! emit_at {
+    ($level:expr, $fail:expr) => {{
+        let fields = $fail.fields();
+        if fields.is_empty() {
+            event!($level, error = %$fail)
+        } else {
+            event!($level, error = %$fail, fields = ?fields)
+        }
+    }};
+}
+
+impl Fail {
+    /// Records this failure as a `tracing` event at the given level.
+    ///
+    /// The full chain is recorded in the `error` field.
+    /// Key-value fields of this layer, if any, are recorded in the `fields` field.
+    pub fn emit(&self, level: Level) {
+        match level {
+            Level::ERROR => emit_at!(Level::ERROR, self),
+            Level::WARN => emit_at!(Level::WARN, self),
+            Level::INFO => emit_at!(Level::INFO, self),
+            Level::DEBUG => emit_at!(Level::DEBUG, self),
+            Level::TRACE => emit_at!(Level::TRACE, self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    use crate::{Fail, FailExt};
+
+    type Fields = Vec<(String, String)>;
+
+    #[derive(Clone, Default)]
+    struct Capture {
+        events: Arc<Mutex<Vec<(Level, Fields)>>>,
+    }
+
+    struct FieldVisitor(Fields);
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut visitor = FieldVisitor(Vec::new());
+            event.record(&mut visitor);
+            self.events
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), visitor.0));
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn emit_records_chain() {
+        let fail = Err::<(), _>(Fail::new("low")).context("high").unwrap_err();
+
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || fail.emit(Level::WARN));
+
+        let events = capture.events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![(
+                Level::WARN,
+                vec![("error".to_string(), "high: low".to_string())]
+            )]
+        );
+    }
+
+    #[test]
+    fn emit_records_fields() {
+        let fail = Fail::new("failed request").with_field("status", 503);
+
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || fail.emit(Level::ERROR));
+
+        let events = capture.events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![(
+                Level::ERROR,
+                vec![
+                    ("error".to_string(), "failed request".to_string()),
+                    ("fields".to_string(), "[(\"status\", \"503\")]".to_string()),
+                ]
+            )]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! `TINY_FAIL_VERBOSE` is read once per process, so each case runs this binary again as a child
+//! with its own environment.
+
+use std::env;
+use std::process::Command;
+
+use tiny_fail::Fail;
+
+const CHILD_ENV: &str = "TINY_FAIL_VERBOSE_ENV_CHILD";
+const MARKER: &str = "rendered=";
+
+#[test]
+fn print_display() {
+    if env::var_os(CHILD_ENV).is_none() {
+        return;
+    }
+
+    let fail = Fail::new("no such file").context("failed open");
+    println!("{}{:?}", MARKER, fail.to_string());
+}
+
+fn display_with(verbose: Option<&str>) -> String {
+    let mut cmd = Command::new(env::current_exe().unwrap());
+    cmd.args([
+        "--exact",
+        "print_display",
+        "--nocapture",
+        "--test-threads=1",
+    ])
+    .env(CHILD_ENV, "1");
+    match verbose {
+        Some(value) => cmd.env("TINY_FAIL_VERBOSE", value),
+        None => cmd.env_remove("TINY_FAIL_VERBOSE"),
+    };
+
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout
+        .lines()
+        // The test harness may print its own progress on the same line.
+        .find_map(|line| line.split_once(MARKER).map(|(_, rendered)| rendered))
+        .unwrap_or_else(|| panic!("no output from child: {}", stdout))
+        .to_owned()
+}
+
+#[test]
+fn verbose_env_makes_display_multi_line() {
+    assert_eq!(
+        display_with(Some("1")),
+        r#""failed open\ncaused by: no such file""#
+    );
+}
+
+#[test]
+fn display_is_single_line_without_verbose_env() {
+    assert_eq!(display_with(None), r#""failed open: no such file""#);
+}
+
+#[test]
+fn display_is_single_line_with_verbose_env_zero() {
+    assert_eq!(display_with(Some("0")), r#""failed open: no such file""#);
+}